@@ -0,0 +1,27 @@
+const searchInput = document.getElementById('page-search-input');
+const suggestionList = document.getElementById('page-search-suggestions');
+if (searchInput !== null && suggestionList !== null) {
+    let pending = null;
+    searchInput.addEventListener('input', function () {
+        // wait for typing to pause before asking for suggestions
+        clearTimeout(pending);
+        pending = setTimeout(async function () {
+            const search = searchInput.value;
+            if (search.trim() === '') {
+                suggestionList.replaceChildren();
+                return;
+            }
+
+            const response = await fetch('/w/api/suggest?search=' + encodeURIComponent(search));
+            if (!response.ok || searchInput.value !== search) {
+                return;
+            }
+            const suggestions = await response.json();
+            suggestionList.replaceChildren(...suggestions.titles.map(function (title) {
+                const option = document.createElement('option');
+                option.value = title.display;
+                return option;
+            }));
+        }, 150);
+    })
+}
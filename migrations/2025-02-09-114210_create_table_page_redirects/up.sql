@@ -0,0 +1,8 @@
+create table page_redirects (
+    page_id     bigint primary key
+        references pages (id)
+            on delete cascade,
+    target      varchar(255) not null
+);
+
+create index page_redirects_target_idx on page_redirects (target);
@@ -0,0 +1,3 @@
+-- 0: user, 1: trusted, 2: admin
+alter table users
+    add column role smallint not null default 0;
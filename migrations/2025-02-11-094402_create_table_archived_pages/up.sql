@@ -0,0 +1,18 @@
+create table archived_pages (
+    id          bigserial primary key,
+    page_id     bigint not null,
+    title       varchar(255) not null,
+    rev_id      bigint not null,
+    root_id     bigint not null,
+    created_on  timestamptz not null,
+
+    deleted_by  bigint not null
+        references users (id)
+            on delete restrict,
+    deleted_on  timestamptz not null
+        default now(),
+    reason      text not null
+        default ''
+);
+
+create index archived_pages_title_idx on archived_pages (title);
@@ -0,0 +1 @@
+drop table archived_revisions;
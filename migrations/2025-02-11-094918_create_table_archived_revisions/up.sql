@@ -0,0 +1,15 @@
+create table archived_revisions (
+    id          bigint primary key,
+    archive_id  bigint not null
+        references archived_pages (id)
+            on delete cascade,
+    parent_id   bigint default null,
+    content_id  bigint not null
+        references contents (id)
+            on delete restrict,
+
+    user_id     bigint not null
+        references users (id)
+            on delete restrict,
+    created_on  timestamptz not null
+);
@@ -0,0 +1,20 @@
+-- action: 0: edit, 1: move
+-- level: 1: logged-in, 2: trusted, 3: admin (unprotected actions have no row)
+create table page_protections (
+    page_id     bigint not null
+        references pages (id)
+            on delete cascade,
+    action      smallint not null,
+    level       smallint not null,
+    expire_on   timestamptz default null,
+    reason      text not null
+        default '',
+
+    user_id     bigint not null
+        references users (id)
+            on delete restrict,
+    created_on  timestamptz not null
+        default now(),
+
+    primary key (page_id, action)
+);
@@ -0,0 +1,32 @@
+update pages
+    set title = p.prefix || pages.title
+    from (values (1, 'Talk:'), (2, 'User:'), (10, 'Template:'), (12, 'Help:'), (14, 'Category:'))
+        as p (id, prefix)
+    where pages.namespace = p.id;
+update archived_pages
+    set title = p.prefix || archived_pages.title
+    from (values (1, 'Talk:'), (2, 'User:'), (10, 'Template:'), (12, 'Help:'), (14, 'Category:'))
+        as p (id, prefix)
+    where archived_pages.namespace = p.id;
+update page_redirects
+    set target = p.prefix || page_redirects.target
+    from (values (1, 'Talk:'), (2, 'User:'), (10, 'Template:'), (12, 'Help:'), (14, 'Category:'))
+        as p (id, prefix)
+    where page_redirects.target_namespace = p.id;
+
+drop index page_redirects_target_idx;
+create index page_redirects_target_idx on page_redirects (target);
+alter table page_redirects
+    drop column target_namespace;
+
+drop index archived_pages_namespace_title_idx;
+create index archived_pages_title_idx on archived_pages (title);
+alter table archived_pages
+    drop column namespace;
+
+alter table pages
+    drop constraint pages_namespace_title_key;
+alter table pages
+    add constraint pages_title_key unique (title);
+alter table pages
+    drop column namespace;
@@ -0,0 +1,34 @@
+-- 0: main, 1: talk, 2: user, 10: template, 12: help, 14: category
+alter table pages
+    add column namespace smallint not null default 0;
+alter table pages
+    drop constraint pages_title_key;
+alter table pages
+    add constraint pages_namespace_title_key unique (namespace, title);
+
+alter table archived_pages
+    add column namespace smallint not null default 0;
+drop index archived_pages_title_idx;
+create index archived_pages_namespace_title_idx on archived_pages (namespace, title);
+
+alter table page_redirects
+    add column target_namespace smallint not null default 0;
+drop index page_redirects_target_idx;
+create index page_redirects_target_idx on page_redirects (target_namespace, target);
+
+-- move existing pages whose titles carry a namespace prefix into that namespace
+update pages
+    set namespace = p.id, title = substr(pages.title, length(p.prefix) + 1)
+    from (values (1, 'Talk:'), (2, 'User:'), (10, 'Template:'), (12, 'Help:'), (14, 'Category:'))
+        as p (id, prefix)
+    where pages.namespace = 0 and pages.title like p.prefix || '_%';
+update archived_pages
+    set namespace = p.id, title = substr(archived_pages.title, length(p.prefix) + 1)
+    from (values (1, 'Talk:'), (2, 'User:'), (10, 'Template:'), (12, 'Help:'), (14, 'Category:'))
+        as p (id, prefix)
+    where archived_pages.namespace = 0 and archived_pages.title like p.prefix || '_%';
+update page_redirects
+    set target_namespace = p.id, target = substr(page_redirects.target, length(p.prefix) + 1)
+    from (values (1, 'Talk:'), (2, 'User:'), (10, 'Template:'), (12, 'Help:'), (14, 'Category:'))
+        as p (id, prefix)
+    where page_redirects.target_namespace = 0 and page_redirects.target like p.prefix || '_%';
@@ -0,0 +1,14 @@
+create table discussion_threads (
+    id          bigserial primary key,
+    namespace   smallint not null,
+    title       varchar(255) not null,
+    subject     varchar(255) not null,
+
+    user_id     bigint not null
+        references users (id)
+            on delete restrict,
+    created_on  timestamptz not null
+        default now()
+);
+
+create index discussion_threads_namespace_title_idx on discussion_threads (namespace, title);
@@ -0,0 +1 @@
+drop table discussion_posts;
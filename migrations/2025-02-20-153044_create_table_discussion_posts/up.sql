@@ -0,0 +1,18 @@
+create table discussion_posts (
+    id          bigserial primary key,
+    thread_id   bigint not null
+        references discussion_threads (id)
+            on delete cascade,
+    parent_id   bigint default null
+        references discussion_posts (id)
+            on delete cascade,
+    body        text not null,
+
+    user_id     bigint not null
+        references users (id)
+            on delete restrict,
+    created_on  timestamptz not null
+        default now()
+);
+
+create index discussion_posts_thread_id_idx on discussion_posts (thread_id);
@@ -0,0 +1,10 @@
+create table page_categories (
+    page_id     bigint not null
+        references pages (id)
+            on delete cascade,
+    category    varchar(255) not null,
+
+    primary key (page_id, category)
+);
+
+create index page_categories_category_idx on page_categories (category);
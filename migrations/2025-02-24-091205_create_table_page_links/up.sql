@@ -0,0 +1,11 @@
+create table page_links (
+    page_id             bigint not null
+        references pages (id)
+            on delete cascade,
+    target_namespace    smallint not null,
+    target              varchar(255) not null,
+
+    primary key (page_id, target_namespace, target)
+);
+
+create index page_links_target_idx on page_links (target_namespace, target);
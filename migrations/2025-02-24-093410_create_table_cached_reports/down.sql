@@ -0,0 +1 @@
+drop table cached_reports;
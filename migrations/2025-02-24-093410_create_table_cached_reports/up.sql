@@ -0,0 +1,12 @@
+create table cached_reports (
+    report      smallint not null,
+    namespace   smallint not null,
+    title       varchar(255) not null,
+    value       bigint not null
+        default 0,
+
+    computed_on timestamptz not null
+        default now(),
+
+    primary key (report, namespace, title)
+);
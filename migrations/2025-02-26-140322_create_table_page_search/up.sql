@@ -0,0 +1,23 @@
+create table page_search (
+    page_id     bigint primary key
+        references pages (id)
+            on delete cascade,
+    title       text not null,
+    body        text not null,
+
+    vector      tsvector not null
+        generated always as (
+            setweight(to_tsvector('english', title), 'A')
+                || setweight(to_tsvector('english', body), 'B')
+        ) stored
+);
+
+create index page_search_vector_idx on page_search using gin (vector);
+
+-- index the current content of existing pages, except for redirects
+insert into page_search (page_id, title, body)
+select pages.id, replace(pages.title, '_', ' '), regexp_replace(convert_from(contents.body, 'UTF8'), '^:text:', '')
+from pages
+    join revisions on revisions.id = pages.rev_id
+    join contents on contents.id = revisions.content_id
+where convert_from(contents.body, 'UTF8') not like ':redirect:%';
@@ -0,0 +1,13 @@
+create table page_formulas (
+    id          bigserial primary key,
+    page_id     bigint not null
+        references pages (id)
+            on delete cascade,
+    source      text not null,
+    -- the normalised tokens of the formula, separated by spaces
+    tokens      text not null,
+    ngrams      text[] not null
+);
+
+create index page_formulas_page_id_idx on page_formulas (page_id);
+create index page_formulas_ngrams_idx on page_formulas using gin (ngrams);
@@ -0,0 +1,3 @@
+drop index pages_title_lower_idx;
+
+drop extension if exists fuzzystrmatch;
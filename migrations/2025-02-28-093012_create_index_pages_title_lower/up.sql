@@ -0,0 +1,4 @@
+create extension if not exists fuzzystrmatch;
+
+-- case-insensitive prefix search of titles, for title suggestions
+create index pages_title_lower_idx on pages (namespace, lower(title) text_pattern_ops);
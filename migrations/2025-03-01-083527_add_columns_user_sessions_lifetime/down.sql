@@ -0,0 +1,3 @@
+alter table user_sessions
+    drop column created_on,
+    drop column remember;
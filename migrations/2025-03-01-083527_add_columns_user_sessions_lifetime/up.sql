@@ -0,0 +1,6 @@
+alter table user_sessions
+    add column created_on timestamptz not null default now(),
+    add column remember boolean not null default false;
+
+-- sessions created so far never expired, so let them run out as if just created
+update user_sessions set expire_on = now() + interval '2 hours' where expire_on is null;
@@ -0,0 +1,4 @@
+delete from user_sessions;
+
+alter table user_sessions drop column token_hash;
+alter table user_sessions add column token varchar(24) primary key;
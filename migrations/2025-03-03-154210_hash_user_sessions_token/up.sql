@@ -0,0 +1,5 @@
+-- plaintext tokens are not kept, so existing sessions cannot be converted and are ended instead
+delete from user_sessions;
+
+alter table user_sessions drop column token;
+alter table user_sessions add column token_hash bytea primary key;
@@ -0,0 +1,15 @@
+create table login_attempts (
+    id              bigserial primary key,
+    -- null if the login did not match any account
+    user_id         bigint
+        references users (id)
+            on delete cascade,
+    -- the username or e-mail address as entered
+    login           text not null,
+    address         text not null,
+    success         boolean not null,
+    attempted_on    timestamptz not null
+);
+
+create index login_attempts_user_id_idx on login_attempts (user_id, attempted_on);
+create index login_attempts_address_idx on login_attempts (address, attempted_on);
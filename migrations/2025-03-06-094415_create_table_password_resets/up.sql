@@ -0,0 +1,11 @@
+create table password_resets (
+    -- the keyed hash of the token sent by e-mail
+    token_hash  bytea primary key,
+    user_id     bigint not null
+        references users (id)
+            on delete cascade,
+    created_on  timestamptz not null,
+    expire_on   timestamptz not null
+);
+
+create index password_resets_user_id_idx on password_resets (user_id);
@@ -0,0 +1,16 @@
+alter table users
+    add column email_verified_on timestamptz;
+
+create table email_verifications (
+    -- the keyed hash of the token sent by e-mail
+    token_hash  bytea primary key,
+    user_id     bigint not null
+        references users (id)
+            on delete cascade,
+    -- the address being verified, which becomes the address of the user once verified
+    email       varchar(320) not null,
+    created_on  timestamptz not null,
+    expire_on   timestamptz not null
+);
+
+create index email_verifications_user_id_idx on email_verifications (user_id);
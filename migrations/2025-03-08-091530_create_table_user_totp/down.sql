@@ -0,0 +1,2 @@
+drop table user_recovery_codes;
+drop table user_totp;
@@ -0,0 +1,22 @@
+create table user_totp (
+    user_id     bigint primary key
+        references users (id)
+            on delete cascade,
+    secret      bytea not null,
+    -- null while the secret is being enrolled and not yet confirmed with a code
+    enabled_on  timestamptz,
+    -- the last time step a code was accepted for, so that no code is used twice
+    last_step   bigint not null default 0
+);
+
+create table user_recovery_codes (
+    id          bigserial primary key,
+    user_id     bigint not null
+        references users (id)
+            on delete cascade,
+    -- hashed like passwords
+    code_hash   bytea not null,
+    used_on     timestamptz
+);
+
+create index user_recovery_codes_user_id_idx on user_recovery_codes (user_id);
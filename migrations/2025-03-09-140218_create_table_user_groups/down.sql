@@ -0,0 +1,17 @@
+alter table users
+    add column role smallint not null default 0;
+
+update users
+    set role = case
+        when exists (
+            select from user_groups
+                where user_groups.user_id = users.id and group_name = 'admin'
+        ) then 2
+        when exists (
+            select from user_groups
+                where user_groups.user_id = users.id and group_name = 'reviewer'
+        ) then 1
+        else 0
+    end;
+
+drop table user_groups;
@@ -0,0 +1,19 @@
+create table user_groups (
+    user_id     bigint not null
+        references users (id)
+            on delete cascade,
+    -- the permissions of each group are configured rather than stored
+    group_name  text not null,
+    added_on    timestamptz not null,
+
+    primary key (user_id, group_name)
+);
+
+-- roles become groups: 1 was trusted, 2 was admin
+insert into user_groups (user_id, group_name, added_on)
+    select id, case role when 1 then 'reviewer' else 'admin' end, now()
+        from users
+        where role > 0;
+
+alter table users
+    drop column role;
@@ -0,0 +1,20 @@
+update pages
+    set namespace = 0, title = p.prefix || pages.title
+    from (values (3, 'User_talk:'), (11, 'Template_talk:'), (13, 'Help_talk:'), (15, 'Category_talk:'))
+        as p (id, prefix)
+    where pages.namespace = p.id;
+update archived_pages
+    set namespace = 0, title = p.prefix || archived_pages.title
+    from (values (3, 'User_talk:'), (11, 'Template_talk:'), (13, 'Help_talk:'), (15, 'Category_talk:'))
+        as p (id, prefix)
+    where archived_pages.namespace = p.id;
+update page_redirects
+    set target_namespace = 0, target = p.prefix || page_redirects.target
+    from (values (3, 'User_talk:'), (11, 'Template_talk:'), (13, 'Help_talk:'), (15, 'Category_talk:'))
+        as p (id, prefix)
+    where page_redirects.target_namespace = p.id;
+update page_links
+    set target_namespace = 0, target = p.prefix || page_links.target
+    from (values (3, 'User_talk:'), (11, 'Template_talk:'), (13, 'Help_talk:'), (15, 'Category_talk:'))
+        as p (id, prefix)
+    where page_links.target_namespace = p.id;
@@ -0,0 +1,22 @@
+-- 3: user talk, 11: template talk, 13: help talk, 15: category talk
+-- move existing pages whose titles carry one of the new prefixes into that namespace
+update pages
+    set namespace = p.id, title = substr(pages.title, length(p.prefix) + 1)
+    from (values (3, 'User_talk:'), (11, 'Template_talk:'), (13, 'Help_talk:'), (15, 'Category_talk:'))
+        as p (id, prefix)
+    where pages.namespace = 0 and pages.title like replace(p.prefix, '_', '\_') || '_%';
+update archived_pages
+    set namespace = p.id, title = substr(archived_pages.title, length(p.prefix) + 1)
+    from (values (3, 'User_talk:'), (11, 'Template_talk:'), (13, 'Help_talk:'), (15, 'Category_talk:'))
+        as p (id, prefix)
+    where archived_pages.namespace = 0 and archived_pages.title like replace(p.prefix, '_', '\_') || '_%';
+update page_redirects
+    set target_namespace = p.id, target = substr(page_redirects.target, length(p.prefix) + 1)
+    from (values (3, 'User_talk:'), (11, 'Template_talk:'), (13, 'Help_talk:'), (15, 'Category_talk:'))
+        as p (id, prefix)
+    where page_redirects.target_namespace = 0 and page_redirects.target like replace(p.prefix, '_', '\_') || '_%';
+update page_links
+    set target_namespace = p.id, target = substr(page_links.target, length(p.prefix) + 1)
+    from (values (3, 'User_talk:'), (11, 'Template_talk:'), (13, 'Help_talk:'), (15, 'Category_talk:'))
+        as p (id, prefix)
+    where page_links.target_namespace = 0 and page_links.target like replace(p.prefix, '_', '\_') || '_%';
@@ -0,0 +1 @@
+drop table reset_requests;
@@ -0,0 +1,12 @@
+create table reset_requests (
+    id              bigserial primary key,
+    -- null if the request did not match any account
+    user_id         bigint
+        references users (id)
+            on delete cascade,
+    address         text not null,
+    requested_on    timestamptz not null
+);
+
+create index reset_requests_user_id_idx on reset_requests (user_id, requested_on);
+create index reset_requests_address_idx on reset_requests (address, requested_on);
@@ -2,7 +2,16 @@ use std::{ops::Deref, path::PathBuf, sync::Arc};
 
 use axum::extract::{FromRequestParts, State};
 
-use crate::{asset::Assets, db::Db, render::Renderer, Error};
+use crate::{
+    asset::Assets,
+    auth::{PasswordPolicy, PermissionConfig},
+    db::Db,
+    mail::MailConfig,
+    model::user::SessionConfig,
+    render::Renderer,
+    title::TitleConfig,
+    Error,
+};
 
 use self::detail::ConfigBuilder;
 
@@ -44,8 +53,26 @@ pub struct Config {
 
     pub server_url: String,
     pub database_url: String,
+    /// The URL under which users reach the wiki, for links sent by e-mail.
+    pub public_url: String,
+    /// The header a reverse proxy puts the address of the client into, such as
+    /// `X-Forwarded-For`, or `None` to use the address of the connecting peer.
+    pub client_ip_header: Option<String>,
 
     pub assets_dir: PathBuf,
+
+    pub titles: TitleConfig,
+
+    /// Whether visitors may create their own accounts, rather than only administrators.
+    pub allow_registration: bool,
+    /// Whether users must verify their e-mail address before they may edit pages.
+    pub require_verified_email: bool,
+    /// Which permissions the groups of users grant.
+    pub permissions: PermissionConfig,
+    pub sessions: SessionConfig,
+    pub passwords: PasswordPolicy,
+
+    pub mail: MailConfig,
 }
 
 impl Config {
@@ -60,9 +87,23 @@ impl Config {
 
 #[doc(hidden)]
 mod detail {
-    use std::path::PathBuf;
+    use std::{
+        collections::{HashMap, HashSet},
+        path::PathBuf,
+        str::FromStr,
+        sync::Arc,
+    };
 
-    use crate::{app::Config, Error};
+    use chrono::TimeDelta;
+
+    use crate::{
+        app::Config,
+        auth::{hash::Hasher, PasswordPolicy, Permission, PermissionConfig},
+        mail::{MailConfig, OutboxTransport, SmtpTransport, Transport},
+        model::user::SessionConfig,
+        title::TitleConfig,
+        Error,
+    };
 
     #[derive(Default)]
     pub struct ConfigBuilder {
@@ -70,23 +111,181 @@ mod detail {
 
         pub server_url: Option<String>,
         pub database_url: Option<String>,
+        pub public_url: Option<String>,
+        pub client_ip_header: Option<String>,
 
         pub assets_dir: Option<PathBuf>,
+
+        pub capitalize_titles: Option<bool>,
+
+        pub allow_registration: Option<bool>,
+        pub require_verified_email: Option<bool>,
+        pub group_permissions: HashMap<String, HashSet<Permission>>,
+        pub session_idle: Option<TimeDelta>,
+        pub session_absolute: Option<TimeDelta>,
+        pub session_remembered_idle: Option<TimeDelta>,
+        pub session_remembered_absolute: Option<TimeDelta>,
+        pub session_key: Option<Box<[u8]>>,
+        pub secure_cookies: Option<bool>,
+        pub password_min_length: Option<usize>,
+        pub banned_passwords: Option<HashSet<String>>,
+        pub password_hasher: Option<Hasher>,
+        pub password_pepper: Option<Box<[u8]>>,
+
+        pub mail_from: Option<String>,
+        pub mail_transport: Option<Arc<dyn Transport>>,
     }
 
     impl ConfigBuilder {
         pub fn build(self) -> Result<Config, Error> {
+            let server_url = self
+                .server_url
+                .map_or_else(|| std::env::var("SERVER_URL"), Ok)?;
+
             Ok(Config {
                 title: self.title.unwrap_or_else(|| "Euclidon".to_string()),
 
-                server_url: self
-                    .server_url
-                    .map_or_else(|| std::env::var("SERVER_URL"), Ok)?,
+                public_url: self
+                    .public_url
+                    .or_else(|| std::env::var("PUBLIC_URL").ok())
+                    .unwrap_or_else(|| format!("http://{server_url}"))
+                    .trim_end_matches('/')
+                    .to_string(),
+                server_url,
+                client_ip_header: self
+                    .client_ip_header
+                    .or_else(|| std::env::var("CLIENT_IP_HEADER").ok())
+                    .filter(|header| !header.is_empty()),
                 database_url: self
                     .database_url
                     .map_or_else(|| std::env::var("DATABASE_URL"), Ok)?,
 
                 assets_dir: self.assets_dir.unwrap_or_else(|| PathBuf::from("assets/")),
+
+                titles: TitleConfig {
+                    capitalize: self
+                        .capitalize_titles
+                        .or_else(|| env_flag("CAPITALIZE_TITLES"))
+                        .unwrap_or(false),
+                },
+
+                allow_registration: self
+                    .allow_registration
+                    .or_else(|| env_flag("ALLOW_REGISTRATION"))
+                    .unwrap_or(true),
+                require_verified_email: self
+                    .require_verified_email
+                    .or_else(|| env_flag("REQUIRE_VERIFIED_EMAIL"))
+                    .unwrap_or(false),
+                permissions: {
+                    let mut permissions = PermissionConfig::default();
+                    if let Ok(path) = std::env::var("PERMISSIONS_FILE") {
+                        permissions.parse_into(&std::fs::read_to_string(path)?)?;
+                    }
+                    permissions.groups.extend(self.group_permissions);
+                    permissions
+                },
+                sessions: {
+                    let default = SessionConfig::default();
+                    SessionConfig {
+                        idle: self
+                            .session_idle
+                            .or_else(|| env_seconds("SESSION_IDLE_SECONDS"))
+                            .unwrap_or(default.idle),
+                        absolute: self
+                            .session_absolute
+                            .or_else(|| env_seconds("SESSION_ABSOLUTE_SECONDS"))
+                            .unwrap_or(default.absolute),
+                        remembered_idle: self
+                            .session_remembered_idle
+                            .or_else(|| env_seconds("SESSION_REMEMBERED_IDLE_SECONDS"))
+                            .unwrap_or(default.remembered_idle),
+                        remembered_absolute: self
+                            .session_remembered_absolute
+                            .or_else(|| env_seconds("SESSION_REMEMBERED_ABSOLUTE_SECONDS"))
+                            .unwrap_or(default.remembered_absolute),
+
+                        key: match self.session_key.or_else(|| {
+                            std::env::var("SESSION_KEY")
+                                .ok()
+                                .map(|key| key.into_bytes().into_boxed_slice())
+                        }) {
+                            Some(key) => key,
+                            // links sent by e-mail are signed with the key, and would silently
+                            // stop working whenever the server restarts
+                            None if self.mail_transport.is_none()
+                                && std::env::var("SMTP_ADDRESS").is_ok() =>
+                            {
+                                return Err(Error::MissingConfig(
+                                    "SESSION_KEY",
+                                    "when sending e-mail over SMTP",
+                                ));
+                            }
+                            None => {
+                                println!(
+                                    "> SESSION_KEY is not set, sessions as well as password reset \
+                                     and e-mail verification links will end when the server restarts"
+                                );
+                                default.key
+                            }
+                        },
+                        secure_cookie: self
+                            .secure_cookies
+                            .or_else(|| env_flag("SECURE_COOKIES"))
+                            .unwrap_or(default.secure_cookie),
+                    }
+                },
+                passwords: PasswordPolicy {
+                    min_length: self
+                        .password_min_length
+                        .or_else(|| env_number("PASSWORD_MIN_LENGTH"))
+                        .unwrap_or(PasswordPolicy::MIN_LENGTH),
+                    banned: match self.banned_passwords {
+                        Some(banned) => banned,
+                        None => {
+                            let mut banned = PasswordPolicy::common_passwords();
+                            if let Ok(path) = std::env::var("PASSWORD_BANNED_FILE") {
+                                banned.extend(
+                                    std::fs::read_to_string(path)?
+                                        .lines()
+                                        .map(str::trim)
+                                        .filter(|line| !line.is_empty())
+                                        .map(str::to_lowercase),
+                                );
+                            }
+                            banned
+                        }
+                    },
+                    hasher: self.password_hasher.unwrap_or_else(|| {
+                        Hasher::new_argon2id(
+                            env_number("ARGON2_MEMORY_KIB").unwrap_or(Hasher::ARGON2_MEMORY),
+                            env_number("ARGON2_ITERATIONS").unwrap_or(Hasher::ARGON2_ITERATIONS),
+                            env_number("ARGON2_PARALLELISM").unwrap_or(Hasher::ARGON2_PARALLELISM),
+                            Hasher::ARGON2_LEN,
+                        )
+                    }),
+                    pepper: self.password_pepper.or_else(|| {
+                        std::env::var("PASSWORD_PEPPER")
+                            .ok()
+                            .map(|pepper| pepper.into_bytes().into_boxed_slice())
+                    }),
+                },
+
+                mail: MailConfig {
+                    from: self
+                        .mail_from
+                        .or_else(|| std::env::var("MAIL_FROM").ok())
+                        .unwrap_or_else(|| "wiki@localhost".to_string()),
+                    transport: self.mail_transport.unwrap_or_else(|| {
+                        match std::env::var("SMTP_ADDRESS") {
+                            Ok(address) => Arc::new(SmtpTransport::new(address)),
+                            Err(_) => Arc::new(OutboxTransport::new(
+                                std::env::var("MAIL_OUTBOX_DIR")
+                                    .map_or_else(|_| PathBuf::from("outbox/"), PathBuf::from),
+                            )),
+                        }
+                    }),
+                },
             })
         }
 
@@ -105,9 +304,147 @@ mod detail {
             self
         }
 
+        /// Sets the URL under which users reach the wiki, such as `https://example.org`.
+        pub fn with_public_url(mut self, public_url: String) -> Self {
+            self.public_url = Some(public_url);
+            self
+        }
+
+        /// Sets the header a reverse proxy puts the address of the client into, for throttling
+        /// by address when all connections come from the proxy.
+        pub fn with_client_ip_header(mut self, client_ip_header: String) -> Self {
+            self.client_ip_header = Some(client_ip_header);
+            self
+        }
+
         pub fn with_assets_dir(mut self, assets_dir: PathBuf) -> Self {
             self.assets_dir = Some(assets_dir);
             self
         }
+
+        pub fn with_capitalize_titles(mut self, capitalize_titles: bool) -> Self {
+            self.capitalize_titles = Some(capitalize_titles);
+            self
+        }
+
+        pub fn with_allow_registration(mut self, allow_registration: bool) -> Self {
+            self.allow_registration = Some(allow_registration);
+            self
+        }
+
+        pub fn with_require_verified_email(mut self, require_verified_email: bool) -> Self {
+            self.require_verified_email = Some(require_verified_email);
+            self
+        }
+
+        /// Sets the permissions granted by a group, replacing its default or configured ones.
+        pub fn with_group_permissions(
+            mut self,
+            group: String,
+            permissions: HashSet<Permission>,
+        ) -> Self {
+            _ = self.group_permissions.insert(group, permissions);
+            self
+        }
+
+        /// Sets how long a session lasts without activity.
+        pub fn with_session_idle(mut self, idle: TimeDelta) -> Self {
+            self.session_idle = Some(idle);
+            self
+        }
+
+        /// Sets how long a session lasts after logging in, regardless of activity.
+        pub fn with_session_absolute(mut self, absolute: TimeDelta) -> Self {
+            self.session_absolute = Some(absolute);
+            self
+        }
+
+        /// Sets how long a session lasts without activity if the user asked to be remembered.
+        pub fn with_session_remembered_idle(mut self, idle: TimeDelta) -> Self {
+            self.session_remembered_idle = Some(idle);
+            self
+        }
+
+        /// Sets how long a session lasts after logging in if the user asked to be remembered.
+        pub fn with_session_remembered_absolute(mut self, absolute: TimeDelta) -> Self {
+            self.session_remembered_absolute = Some(absolute);
+            self
+        }
+
+        /// Sets the key for hashing session tokens, which should be long and random.
+        pub fn with_session_key(mut self, key: Box<[u8]>) -> Self {
+            self.session_key = Some(key);
+            self
+        }
+
+        /// Sets whether the session cookie is only sent over HTTPS.
+        pub fn with_secure_cookies(mut self, secure_cookies: bool) -> Self {
+            self.secure_cookies = Some(secure_cookies);
+            self
+        }
+
+        /// Sets the minimum length of new passwords in characters.
+        pub fn with_password_min_length(mut self, min_length: usize) -> Self {
+            self.password_min_length = Some(min_length);
+            self
+        }
+
+        /// Sets the passwords which may not be chosen, replacing the built-in list of common ones.
+        pub fn with_banned_passwords(mut self, banned: HashSet<String>) -> Self {
+            self.banned_passwords = Some(
+                banned
+                    .into_iter()
+                    .map(|password| password.to_lowercase())
+                    .collect(),
+            );
+            self
+        }
+
+        /// Sets the hasher for new passwords, along with the parameters below which existing
+        /// passwords are hashed again.
+        pub fn with_password_hasher(mut self, password_hasher: Hasher) -> Self {
+            self.password_hasher = Some(password_hasher);
+            self
+        }
+
+        /// Sets a secret mixed into passwords before hashing, which must then never change.
+        pub fn with_password_pepper(mut self, pepper: Box<[u8]>) -> Self {
+            self.password_pepper = Some(pepper);
+            self
+        }
+
+        /// Sets the address e-mail is sent from.
+        pub fn with_mail_from(mut self, from: String) -> Self {
+            self.mail_from = Some(from);
+            self
+        }
+
+        /// Sets how e-mail is delivered, instead of the outbox directory or SMTP server.
+        pub fn with_mail_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+            self.mail_transport = Some(transport);
+            self
+        }
+    }
+
+    /// Reads a boolean option from the environment, accepting `true`/`false` and `1`/`0`.
+    fn env_flag(name: &str) -> Option<bool> {
+        match std::env::var(name).ok()?.trim() {
+            "1" | "true" => Some(true),
+            "0" | "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Reads a number from the environment.
+    fn env_number<T>(name: &str) -> Option<T>
+    where
+        T: FromStr,
+    {
+        std::env::var(name).ok()?.trim().parse().ok()
+    }
+
+    /// Reads a duration in whole seconds from the environment.
+    fn env_seconds(name: &str) -> Option<TimeDelta> {
+        TimeDelta::try_seconds(std::env::var(name).ok()?.trim().parse().ok()?)
     }
 }
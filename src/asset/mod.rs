@@ -73,7 +73,7 @@ impl Assets {
     pub fn load_transient(&self, loc: &Loc) -> Result<Arc<Asset>, Error> {
         let path = self.root.join(&loc.path);
         Ok(Arc::new(Asset::new(
-            AssetKind::from_extension(path.extension().map(OsStr::to_str).flatten()),
+            AssetKind::from_extension(path.extension().and_then(OsStr::to_str)),
             fs::read(path)?.into_boxed_slice(),
         )))
     }
@@ -3,16 +3,31 @@ use sha3::Sha3_512;
 
 use super::PasswordError;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Hasher {
     Pbkdf2 {
         algorithm: Algorithm,
         rounds: u32,
         len: usize,
     },
+    Argon2id {
+        /// The memory cost in KiB.
+        memory: u32,
+        /// The number of passes over the memory.
+        iterations: u32,
+        /// The number of lanes hashed in parallel.
+        parallelism: u32,
+        len: usize,
+    },
 }
 
 impl Hasher {
+    /// The default Argon2id parameters, as recommended by OWASP.
+    pub const ARGON2_MEMORY: u32 = 19 * 1024;
+    pub const ARGON2_ITERATIONS: u32 = 2;
+    pub const ARGON2_PARALLELISM: u32 = 1;
+    pub const ARGON2_LEN: usize = 32;
+
     pub fn new_pbkdf2(algorithm: Algorithm, rounds: u32, len: usize) -> Self {
         Self::Pbkdf2 {
             algorithm,
@@ -21,11 +36,59 @@ impl Hasher {
         }
     }
 
+    pub fn new_argon2id(memory: u32, iterations: u32, parallelism: u32, len: usize) -> Self {
+        Self::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+            len,
+        }
+    }
+
+    /// Returns whether hashes made by `self` are weaker than those made by `current`, and should
+    /// be replaced when the password is next known.
+    ///
+    /// Any other kind of hasher is considered weaker than Argon2id.
+    pub fn is_weaker_than(&self, current: &Self) -> bool {
+        match (self, current) {
+            (
+                Self::Argon2id {
+                    memory,
+                    iterations,
+                    parallelism,
+                    len,
+                },
+                Self::Argon2id {
+                    memory: current_memory,
+                    iterations: current_iterations,
+                    parallelism: current_parallelism,
+                    len: current_len,
+                },
+            ) => {
+                memory < current_memory
+                    || iterations < current_iterations
+                    || parallelism < current_parallelism
+                    || len < current_len
+            }
+            (
+                Self::Pbkdf2 { rounds, len, .. },
+                Self::Pbkdf2 {
+                    rounds: current_rounds,
+                    len: current_len,
+                    ..
+                },
+            ) => rounds < current_rounds || len < current_len,
+            (Self::Pbkdf2 { .. }, Self::Argon2id { .. }) => true,
+            (Self::Argon2id { .. }, Self::Pbkdf2 { .. }) => false,
+        }
+    }
+
     pub(super) fn parse<'a>(
         segments: &mut impl Iterator<Item = &'a str>,
     ) -> Result<Self, PasswordError> {
         match segments.next() {
             Some("pbkdf2") => Self::parse_pbkdf2(segments),
+            Some("argon2id") => Self::parse_argon2id(segments),
             _ => Err(PasswordError::HasherComp),
         }
     }
@@ -37,29 +100,38 @@ impl Hasher {
                 rounds,
                 len,
             } => Self::hash_pbkdf2(password, salt, *algorithm, *rounds, *len),
+            Self::Argon2id {
+                memory,
+                iterations,
+                parallelism,
+                len,
+            } => Self::hash_argon2id(password, salt, *memory, *iterations, *parallelism, *len),
         }
     }
 
     fn parse_pbkdf2<'a>(
         segments: &mut impl Iterator<Item = &'a str>,
     ) -> Result<Self, PasswordError> {
-        let algorithm = Algorithm::from_name(segments.next().ok_or(PasswordError::HasherComp)?)
-            .ok_or(PasswordError::HasherComp)?;
-        let rounds = segments
+        let algorithm = segments
             .next()
-            .ok_or(PasswordError::HasherComp)?
-            .parse()
-            .map_err(|_| PasswordError::HasherComp)?;
-        let len = segments
-            .next()
-            .ok_or(PasswordError::HasherComp)?
-            .parse()
-            .map_err(|_| PasswordError::HasherComp)?;
+            .and_then(Algorithm::from_name)
+            .ok_or(PasswordError::HasherParam("algorithm"))?;
 
         Ok(Self::Pbkdf2 {
             algorithm,
-            rounds,
-            len,
+            rounds: parse_param(segments, "rounds")?,
+            len: parse_param(segments, "len")?,
+        })
+    }
+
+    fn parse_argon2id<'a>(
+        segments: &mut impl Iterator<Item = &'a str>,
+    ) -> Result<Self, PasswordError> {
+        Ok(Self::Argon2id {
+            memory: parse_param(segments, "memory")?,
+            iterations: parse_param(segments, "iterations")?,
+            parallelism: parse_param(segments, "parallelism")?,
+            len: parse_param(segments, "len")?,
         })
     }
 
@@ -82,6 +154,48 @@ impl Hasher {
 
         Ok(buf.into_boxed_slice())
     }
+
+    fn hash_argon2id(
+        password: &[u8],
+        salt: &[u8],
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+        len: usize,
+    ) -> Result<Box<[u8]>, HashError> {
+        let params = argon2::Params::new(memory, iterations, parallelism, Some(len))
+            .map_err(HashError::Argon2)?;
+        let mut buf = vec![0; len];
+        argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+            .hash_password_into(password, salt, &mut buf)
+            .map_err(HashError::Argon2)?;
+
+        Ok(buf.into_boxed_slice())
+    }
+}
+
+fn parse_param<'a, T>(
+    segments: &mut impl Iterator<Item = &'a str>,
+    name: &'static str,
+) -> Result<T, PasswordError>
+where
+    T: std::str::FromStr,
+{
+    segments
+        .next()
+        .and_then(|segment| segment.parse().ok())
+        .ok_or(PasswordError::HasherParam(name))
+}
+
+impl Default for Hasher {
+    fn default() -> Self {
+        Self::new_argon2id(
+            Self::ARGON2_MEMORY,
+            Self::ARGON2_ITERATIONS,
+            Self::ARGON2_PARALLELISM,
+            Self::ARGON2_LEN,
+        )
+    }
 }
 
 impl std::fmt::Display for Hasher {
@@ -92,6 +206,12 @@ impl std::fmt::Display for Hasher {
                 rounds,
                 len,
             } => write!(f, "pbkdf2:{}:{rounds}:{len}", algorithm.name()),
+            Hasher::Argon2id {
+                memory,
+                iterations,
+                parallelism,
+                len,
+            } => write!(f, "argon2id:{memory}:{iterations}:{parallelism}:{len}"),
         }
     }
 }
@@ -120,4 +240,6 @@ impl Algorithm {
 pub enum HashError {
     #[error("invalid buffer length '{0}' specified")]
     BufLen(usize),
+    #[error("argon2 failed: {0}")]
+    Argon2(argon2::Error),
 }
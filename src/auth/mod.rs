@@ -1,4 +1,11 @@
 pub mod hash;
 mod password;
+mod permission;
+mod policy;
+pub mod totp;
 
-pub use self::password::{Password, PasswordError, PasswordV1};
+pub use self::{
+    password::{Password, PasswordError, PasswordV1},
+    permission::{Permission, PermissionConfig, PermissionError, Permissions},
+    policy::{PasswordPolicy, PolicyError},
+};
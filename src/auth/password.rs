@@ -7,27 +7,41 @@ use diesel::{
     serialize::ToSql,
     sql_types::Binary,
 };
+use hmac::{Hmac, Mac};
 use rand_chacha::ChaCha12Rng;
 use rand_core::{RngCore, SeedableRng};
+use sha3::Sha3_256;
+use subtle::ConstantTimeEq;
 
-use super::hash::{Algorithm, HashError, Hasher};
+use super::{
+    hash::{HashError, Hasher},
+    PasswordPolicy,
+};
 
 #[derive(Debug, Clone, Default, AsExpression, FromSqlRow)]
 #[diesel(sql_type = Binary, check_for_backend(Pg))]
 pub enum Password {
     V1(PasswordV1),
+    /// Like `V1`, but hashing the password keyed with the pepper of the policy.
+    Peppered(PasswordV1),
 
     #[default]
     Invalid,
 }
 
 impl Password {
+    /// Hashes `password` as required by `policy`, peppering it if the policy has a pepper.
     pub fn generate_current(
         password: &str,
         salt: Option<Box<[u8]>>,
-        hasher: Option<Hasher>,
+        policy: &PasswordPolicy,
     ) -> Result<Self, HashError> {
-        Self::generate_v1(password, salt, hasher)
+        match &policy.pepper {
+            Some(pepper) => {
+                Self::generate_peppered(password, salt, Some(policy.hasher.clone()), pepper)
+            }
+            None => Self::generate_v1(password, salt, Some(policy.hasher.clone())),
+        }
     }
 
     pub fn generate_v1(
@@ -35,7 +49,24 @@ impl Password {
         salt: Option<Box<[u8]>>,
         hasher: Option<Hasher>,
     ) -> Result<Self, HashError> {
-        Ok(Self::V1(PasswordV1::generate(password, salt, hasher)?))
+        Ok(Self::V1(PasswordV1::generate(
+            password.as_bytes(),
+            salt,
+            hasher,
+        )?))
+    }
+
+    pub fn generate_peppered(
+        password: &str,
+        salt: Option<Box<[u8]>>,
+        hasher: Option<Hasher>,
+        pepper: &[u8],
+    ) -> Result<Self, HashError> {
+        Ok(Self::Peppered(PasswordV1::generate(
+            &apply_pepper(password, pepper),
+            salt,
+            hasher,
+        )?))
     }
 
     pub fn from_encoded(encoded: &[u8]) -> Result<Self, PasswordError> {
@@ -51,22 +82,52 @@ impl Password {
         }
     }
 
-    pub fn compare(&self, other: &str) -> Result<bool, PasswordError> {
+    /// Checks whether `other` is this password, in constant time with regard to the hashes.
+    pub fn compare(&self, other: &str, policy: &PasswordPolicy) -> Result<bool, PasswordError> {
         match self {
-            Self::V1(password) => password.compare(other),
+            Self::V1(password) => password.compare(other.as_bytes()),
+            Self::Peppered(password) => {
+                let pepper = policy.pepper.as_ref().ok_or(PasswordError::Pepper)?;
+                password.compare(&apply_pepper(other, pepper))
+            }
             Self::Invalid => Ok(false),
         }
     }
 
+    /// Takes as long as comparing `other` with a password hashed by the current hasher, for
+    /// logins of accounts which do not exist.
+    pub fn compare_dummy(other: &str, policy: &PasswordPolicy) -> Result<(), HashError> {
+        _ = Self::generate_current(other, None, policy)?;
+        Ok(())
+    }
+
     fn parse_variant<'a>(
         segments: &mut impl Iterator<Item = &'a str>,
     ) -> Result<Self, PasswordError> {
-        Ok(match segments.next() {
+        let password = match segments.next() {
             Some("1") => Self::V1(PasswordV1::parse(segments)?),
+            Some("peppered") => Self::Peppered(PasswordV1::parse(segments)?),
             Some("invalid") => Self::Invalid,
 
             _ => return Err(PasswordError::VariantComp),
-        })
+        };
+        if segments.next().is_some() {
+            return Err(PasswordError::TrailingComp);
+        }
+
+        Ok(password)
+    }
+
+    /// Returns whether the password was stored more weakly than `policy` requires, and should be
+    /// hashed again the next time it is entered.
+    pub fn needs_rehash(&self, policy: &PasswordPolicy) -> bool {
+        match self {
+            Self::V1(password) => {
+                policy.pepper.is_some() || password.hasher.is_weaker_than(&policy.hasher)
+            }
+            Self::Peppered(password) => password.hasher.is_weaker_than(&policy.hasher),
+            Self::Invalid => false,
+        }
     }
 
     pub fn is_valid(&self) -> bool {
@@ -79,7 +140,8 @@ where
     S: AsRef<str>,
 {
     fn from(value: S) -> Self {
-        Self::generate_current(value.as_ref(), None, None).unwrap_or(Self::Invalid)
+        Self::generate_current(value.as_ref(), None, &PasswordPolicy::default())
+            .unwrap_or(Self::Invalid)
     }
 }
 
@@ -87,6 +149,7 @@ impl std::fmt::Display for Password {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::V1(password) => write!(f, ":1:{password}"),
+            Self::Peppered(password) => write!(f, ":peppered:{password}"),
             Self::Invalid => write!(f, ":invalid"),
         }
     }
@@ -116,6 +179,15 @@ where
     }
 }
 
+/// Keys `password` with `pepper`, so that it cannot be checked against a stored hash without the
+/// pepper.
+fn apply_pepper(password: &str, pepper: &[u8]) -> Vec<u8> {
+    let mut mac =
+        Hmac::<Sha3_256>::new_from_slice(pepper).expect("HMAC should accept keys of any length");
+    mac.update(password.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
 fn generate_salt() -> Box<[u8]> {
     let mut salt = [0; 16];
     ChaCha12Rng::from_os_rng().fill_bytes(&mut salt);
@@ -131,15 +203,14 @@ pub struct PasswordV1 {
 
 impl PasswordV1 {
     fn generate(
-        password: &str,
+        password: &[u8],
         salt: Option<Box<[u8]>>,
         hasher: Option<Hasher>,
     ) -> Result<Self, HashError> {
-        let hasher =
-            hasher.unwrap_or_else(|| Hasher::new_pbkdf2(Algorithm::HmacSha3_512, 10000, 64));
-        let salt = salt.unwrap_or_else(|| generate_salt());
+        let hasher = hasher.unwrap_or_default();
+        let salt = salt.unwrap_or_else(generate_salt);
         Ok(Self {
-            hash: hasher.hash(password.as_bytes(), &salt)?,
+            hash: hasher.hash(password, &salt)?,
             salt,
             hasher,
         })
@@ -153,14 +224,17 @@ impl PasswordV1 {
             .into_boxed_slice();
         let hash = BASE64_URL_SAFE
             .decode(segments.next().ok_or(PasswordError::HashComp)?)
-            .map_err(|_| PasswordError::SaltComp)?
+            .map_err(|_| PasswordError::HashComp)?
             .into_boxed_slice();
 
         Ok(Self { hasher, salt, hash })
     }
 
-    fn compare(&self, other: &str) -> Result<bool, PasswordError> {
-        Ok(self.hash == self.hasher.hash(other.as_bytes(), &self.salt)?)
+    fn compare(&self, other: &[u8]) -> Result<bool, PasswordError> {
+        Ok(self
+            .hash
+            .ct_eq(&self.hasher.hash(other, &self.salt)?)
+            .into())
     }
 }
 
@@ -184,10 +258,16 @@ pub enum PasswordError {
     VariantComp,
     #[error("invalid hashing algorithm")]
     HasherComp,
+    #[error("invalid hasher parameter '{0}'")]
+    HasherParam(&'static str),
     #[error("invalid salt")]
     SaltComp,
     #[error("invalid password hash")]
     HashComp,
+    #[error("unexpected components after the password hash")]
+    TrailingComp,
+    #[error("the password is peppered, but no pepper is configured")]
+    Pepper,
 
     #[error(transparent)]
     Hash(#[from] HashError),
@@ -195,11 +275,69 @@ pub enum PasswordError {
 
 #[test]
 fn password_test() {
-    let password = Password::generate_current("hello", None, None).unwrap();
+    let policy = PasswordPolicy::default();
+    let password = Password::generate_current("hello", None, &policy).unwrap();
 
     let encoded = format!("{password}");
     println!("{encoded}");
 
     let decoded = Password::from_encoded(encoded.as_bytes()).unwrap();
     println!("{decoded}");
+    assert!(decoded.compare("hello", &policy).unwrap());
+
+    assert!(matches!(
+        Password::from_encoded(b":1:argon2id:19456:2:1:32:c2FsdA==:not base64"),
+        Err(PasswordError::HashComp)
+    ));
+    assert!(matches!(
+        Password::from_encoded(b":1:argon2id:19456:x:1:32:c2FsdA==:aGFzaA=="),
+        Err(PasswordError::HasherParam("iterations"))
+    ));
+    assert!(matches!(
+        Password::from_encoded(b":1:argon2id:19456:2:1:32:c2FsdA==:aGFzaA==:extra"),
+        Err(PasswordError::TrailingComp)
+    ));
+}
+
+#[test]
+fn password_rehash_test() {
+    use super::hash::Algorithm;
+
+    let policy = PasswordPolicy::default();
+    let old = Password::generate_v1(
+        "hello",
+        None,
+        Some(Hasher::new_pbkdf2(Algorithm::HmacSha3_512, 10000, 64)),
+    )
+    .unwrap();
+    assert!(old.compare("hello", &policy).unwrap());
+    assert!(old.needs_rehash(&policy));
+
+    let new = Password::generate_current("hello", None, &policy).unwrap();
+    assert!(format!("{new}").starts_with(":1:argon2id:19456:2:1:32:"));
+    assert!(new.compare("hello", &policy).unwrap());
+    assert!(!new.compare("world", &policy).unwrap());
+    assert!(!new.needs_rehash(&policy));
+
+    let stronger = PasswordPolicy {
+        hasher: Hasher::new_argon2id(64 * 1024, 3, 1, 32),
+        ..PasswordPolicy::default()
+    };
+    assert!(new.needs_rehash(&stronger));
+
+    let peppered = PasswordPolicy {
+        pepper: Some(b"pepper".to_vec().into_boxed_slice()),
+        ..PasswordPolicy::default()
+    };
+    assert!(new.needs_rehash(&peppered));
+
+    let new = Password::generate_current("hello", None, &peppered).unwrap();
+    let decoded = Password::from_encoded(format!("{new}").as_bytes()).unwrap();
+    assert!(format!("{decoded}").starts_with(":peppered:"));
+    assert!(decoded.compare("hello", &peppered).unwrap());
+    assert!(!decoded.needs_rehash(&peppered));
+    assert!(matches!(
+        decoded.compare("hello", &policy),
+        Err(PasswordError::Pepper)
+    ));
 }
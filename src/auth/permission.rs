@@ -0,0 +1,214 @@
+use std::collections::{HashMap, HashSet};
+
+/// Something a user may be allowed to do, granted through the groups they belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    /// Editing existing pages and taking part in discussions.
+    Edit,
+    /// Creating pages which do not exist yet.
+    Create,
+    Move,
+    /// Deleting pages and restoring deleted ones.
+    Delete,
+    /// Protecting pages, and acting on pages protected at the admin level.
+    Protect,
+    /// Acting on pages protected at the trusted level.
+    Trusted,
+    Block,
+    Import,
+    /// Adding users to groups and removing them.
+    Groups,
+}
+
+impl Permission {
+    pub const ALL: [Self; 9] = [
+        Self::Edit,
+        Self::Create,
+        Self::Move,
+        Self::Delete,
+        Self::Protect,
+        Self::Trusted,
+        Self::Block,
+        Self::Import,
+        Self::Groups,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Edit => "edit",
+            Self::Create => "create",
+            Self::Move => "move",
+            Self::Delete => "delete",
+            Self::Protect => "protect",
+            Self::Trusted => "trusted",
+            Self::Block => "block",
+            Self::Import => "import",
+            Self::Groups => "groups",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|permission| permission.name() == name)
+    }
+}
+
+/// Which permissions each group grants.
+///
+/// Besides the groups users are added to, everyone is implicitly in `*` and every logged in user
+/// is implicitly in `user`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionConfig {
+    pub groups: HashMap<String, HashSet<Permission>>,
+}
+
+impl PermissionConfig {
+    /// The implicit group of everyone, including anonymous visitors.
+    ///
+    /// Actions which record who performed them, such as editing, still require logging in.
+    pub const EVERYONE: &str = "*";
+    /// The implicit group of every logged in user.
+    pub const USER: &str = "user";
+
+    /// Returns the permissions of a user in `groups`, or of an anonymous visitor if `None`.
+    pub fn resolve<'a>(&self, groups: Option<impl IntoIterator<Item = &'a str>>) -> Permissions {
+        let mut granted = self.granted_by(Self::EVERYONE);
+        let logged_in = groups.is_some();
+        if let Some(groups) = groups {
+            granted.extend(self.granted_by(Self::USER));
+            for group in groups {
+                granted.extend(self.granted_by(group));
+            }
+        }
+
+        Permissions { logged_in, granted }
+    }
+
+    /// Replaces the permissions of the groups listed in `text`, one per line as
+    /// `group: permission permission ...`, with `#` starting a comment.
+    pub fn parse_into(&mut self, text: &str) -> Result<(), PermissionError> {
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (group, permissions) = line
+                .split_once(':')
+                .ok_or(PermissionError::Syntax(number + 1))?;
+            let group = group.trim();
+            if group.is_empty() {
+                return Err(PermissionError::Syntax(number + 1));
+            }
+            let permissions = permissions
+                .split([' ', ','])
+                .filter(|name| !name.is_empty())
+                .map(|name| {
+                    Permission::from_name(name)
+                        .ok_or_else(|| PermissionError::Unknown(name.to_string()))
+                })
+                .collect::<Result<_, _>>()?;
+
+            _ = self.groups.insert(group.to_string(), permissions);
+        }
+
+        Ok(())
+    }
+
+    /// Lists the groups users can be added to, leaving out the implicit ones.
+    pub fn assignable(&self) -> Vec<&str> {
+        let mut groups = self
+            .groups
+            .keys()
+            .map(String::as_str)
+            .filter(|group| ![Self::EVERYONE, Self::USER].contains(group))
+            .collect::<Vec<_>>();
+        groups.sort_unstable();
+        groups
+    }
+
+    fn granted_by(&self, group: &str) -> HashSet<Permission> {
+        self.groups.get(group).cloned().unwrap_or_default()
+    }
+}
+
+impl Default for PermissionConfig {
+    /// Anonymous visitors may only read, users may edit and create pages, and the permissions
+    /// grow from `autoconfirmed` over `reviewer` to `admin`, with `bot` for automated edits.
+    fn default() -> Self {
+        use Permission::*;
+
+        Self {
+            groups: [
+                (Self::EVERYONE, vec![]),
+                (Self::USER, vec![Edit, Create]),
+                ("autoconfirmed", vec![Edit, Create, Move]),
+                ("reviewer", vec![Edit, Create, Move, Trusted]),
+                ("admin", Permission::ALL.to_vec()),
+                ("bot", vec![Edit, Create, Move, Import]),
+            ]
+            .into_iter()
+            .map(|(group, permissions)| (group.to_string(), permissions.into_iter().collect()))
+            .collect(),
+        }
+    }
+}
+
+/// The permissions of a user or anonymous visitor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Permissions {
+    pub logged_in: bool,
+    pub granted: HashSet<Permission>,
+}
+
+impl Permissions {
+    pub fn has(&self, permission: Permission) -> bool {
+        self.granted.contains(&permission)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PermissionError {
+    #[error("line {0} of the permissions is not of the form 'group: permission ...'")]
+    Syntax(usize),
+    #[error("unknown permission '{0}'")]
+    Unknown(String),
+}
+
+#[test]
+fn permission_test() {
+    let mut config = PermissionConfig::default();
+    let anonymous = config.resolve(None::<[&str; 0]>);
+    assert!(!anonymous.logged_in);
+    assert!(!anonymous.has(Permission::Edit));
+
+    let user = config.resolve(Some([]));
+    assert!(user.has(Permission::Edit));
+    assert!(!user.has(Permission::Delete));
+    assert!(config.resolve(Some(["admin"])).has(Permission::Delete));
+    // unknown groups grant nothing
+    assert_eq!(config.resolve(Some(["nobody"])), user);
+
+    config
+        .parse_into("# open wiki\n*: edit\nreviewer: edit, delete # no moving\n")
+        .unwrap();
+    assert!(config.resolve(None::<[&str; 0]>).has(Permission::Edit));
+    let reviewer = config.resolve(Some(["reviewer"]));
+    assert!(reviewer.has(Permission::Delete));
+    assert!(!reviewer.has(Permission::Move));
+
+    assert_eq!(
+        config.assignable(),
+        ["admin", "autoconfirmed", "bot", "reviewer"]
+    );
+
+    assert!(matches!(
+        config.parse_into("admin: fly"),
+        Err(PermissionError::Unknown(name)) if name == "fly"
+    ));
+    assert!(matches!(
+        config.parse_into("admin edit"),
+        Err(PermissionError::Syntax(1))
+    ));
+}
@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+
+use super::hash::Hasher;
+
+/// Passwords which are too common to be allowed regardless of their length, compared without
+/// regard to case.
+const COMMON_PASSWORDS: &[&str] = &[
+    "000000",
+    "111111",
+    "123123",
+    "123456",
+    "1234567",
+    "12345678",
+    "123456789",
+    "1234567890",
+    "654321",
+    "666666",
+    "abc123",
+    "admin",
+    "baseball",
+    "dragon",
+    "football",
+    "iloveyou",
+    "letmein",
+    "master",
+    "monkey",
+    "passw0rd",
+    "password",
+    "password1",
+    "princess",
+    "qwerty",
+    "qwerty123",
+    "qwertyuiop",
+    "shadow",
+    "sunshine",
+    "trustno1",
+    "welcome",
+];
+
+/// The rules for choosing passwords and for storing them.
+#[derive(Clone)]
+pub struct PasswordPolicy {
+    /// The minimum length of new passwords in characters.
+    pub min_length: usize,
+    /// Passwords which may not be chosen, in lowercase.
+    pub banned: HashSet<String>,
+    /// The hasher for new passwords; existing passwords hashed more weakly are hashed again with
+    /// it when their users log in.
+    pub hasher: Hasher,
+    /// A secret mixed into passwords before hashing, kept out of the database so that a leaked
+    /// database alone is not enough to guess passwords.
+    pub pepper: Option<Box<[u8]>>,
+}
+
+impl PasswordPolicy {
+    pub const MIN_LENGTH: usize = 8;
+
+    /// Returns the built-in list of common passwords, for extending with further ones.
+    pub fn common_passwords() -> HashSet<String> {
+        COMMON_PASSWORDS.iter().map(ToString::to_string).collect()
+    }
+
+    /// Checks whether `password` may be chosen as a new password.
+    pub fn check(&self, password: &str) -> Result<(), PolicyError> {
+        let len = password.chars().count();
+        if len < self.min_length {
+            return Err(PolicyError::TooShort(len, self.min_length));
+        }
+        if self.banned.contains(&password.to_lowercase()) {
+            return Err(PolicyError::Common);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: Self::MIN_LENGTH,
+            banned: Self::common_passwords(),
+            hasher: Hasher::default(),
+            pepper: None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PolicyError {
+    #[error("the password is {0} characters long, but at least {1} are required")]
+    TooShort(usize, usize),
+    #[error("the password is too common, choose another one")]
+    Common,
+}
+
+#[test]
+fn policy_test() {
+    let policy = PasswordPolicy::default();
+
+    assert!(matches!(policy.check(""), Err(PolicyError::TooShort(0, 8))));
+    assert!(matches!(
+        policy.check("short"),
+        Err(PolicyError::TooShort(5, 8))
+    ));
+    assert!(matches!(policy.check("Password"), Err(PolicyError::Common)));
+    assert!(matches!(policy.check("12345678"), Err(PolicyError::Common)));
+    assert!(policy.check("correct horse battery staple").is_ok());
+}
@@ -0,0 +1,183 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use qrcode::{render::svg, QrCode};
+use rand_chacha::ChaCha12Rng;
+use rand_core::{RngCore, SeedableRng};
+use sha1::Sha1;
+
+/// The length of a time step in seconds, as used by common authenticator apps.
+const STEP: i64 = 30;
+const DIGITS: u32 = 6;
+/// The number of steps a code may be off by, to allow for clock drift and slow typing.
+const SKEW: i64 = 1;
+
+/// A time-based one-time password generator as in RFC 6238, using HMAC-SHA1 with 6 digits and
+/// 30 second steps for compatibility with authenticator apps.
+#[derive(Clone)]
+pub struct Totp {
+    pub secret: Vec<u8>,
+}
+
+impl Totp {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self { secret }
+    }
+
+    /// Generates a random secret of 160 bits, the length of an SHA-1 hash as RFC 4226
+    /// recommends.
+    pub fn generate() -> Self {
+        let mut secret = vec![0; 20];
+        ChaCha12Rng::from_os_rng().fill_bytes(&mut secret);
+        Self { secret }
+    }
+
+    /// Returns the time step containing `unix_time`.
+    pub fn step_at(unix_time: i64) -> i64 {
+        unix_time.div_euclid(STEP)
+    }
+
+    /// Computes the code for a time step.
+    pub fn code(&self, step: i64) -> String {
+        let mut mac = Hmac::<Sha1>::new_from_slice(&self.secret)
+            .expect("HMAC should accept keys of any length");
+        mac.update(&step.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+
+        // dynamic truncation as in RFC 4226, section 5.3
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let value = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+
+        format!(
+            "{:0width$}",
+            value % 10u32.pow(DIGITS),
+            width = DIGITS as usize
+        )
+    }
+
+    /// Checks `code` against the steps around `unix_time`, returning the step it matched.
+    ///
+    /// Steps up to and including `last_step` are refused, so that a code cannot be used twice.
+    pub fn verify(&self, code: &str, unix_time: i64, last_step: i64) -> Option<i64> {
+        let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+        if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let current = Self::step_at(unix_time);
+        ((current - SKEW)..=(current + SKEW))
+            .filter(|step| *step > last_step)
+            .find(|step| {
+                let expected = self.code(*step);
+                // compare every digit so that timing does not tell how many were right
+                expected
+                    .bytes()
+                    .zip(code.bytes())
+                    .fold(0, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+            })
+    }
+
+    /// Returns the secret in base 32, for entering into an authenticator app by hand.
+    pub fn secret_base32(&self) -> String {
+        BASE32_NOPAD.encode(&self.secret)
+    }
+
+    /// Returns the `otpauth://` URI for enrolling the secret in an authenticator app.
+    pub fn uri(&self, issuer: &str, account: &str) -> String {
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={DIGITS}&period={STEP}",
+            encode_uri_component(issuer),
+            encode_uri_component(account),
+            self.secret_base32(),
+            encode_uri_component(issuer),
+        )
+    }
+
+    /// Renders the enrollment URI as a QR code in SVG, for scanning with an authenticator app.
+    pub fn qr_svg(&self, issuer: &str, account: &str) -> String {
+        QrCode::new(self.uri(issuer, account).as_bytes())
+            .expect("the enrollment URI should fit in a QR code")
+            .render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .build()
+    }
+}
+
+/// Generates a recovery code such as `k3d9x-mq2pa`, for logging in when the authenticator is lost.
+pub fn generate_recovery_code() -> String {
+    const ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+    let mut rng = ChaCha12Rng::from_os_rng();
+    let mut code = String::with_capacity(11);
+    for i in 0..10 {
+        if i == 5 {
+            code.push('-');
+        }
+        code.push(ALPHABET[(rng.next_u32() % ALPHABET.len() as u32) as usize] as char);
+    }
+
+    code
+}
+
+/// Normalizes an entered recovery code, so that case and spacing do not matter.
+pub fn normalize_recovery_code(code: &str) -> String {
+    let code: String = code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    match code.len() {
+        10 => format!("{}-{}", &code[..5], &code[5..]),
+        _ => code,
+    }
+}
+
+fn encode_uri_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[test]
+fn totp_test() {
+    // the SHA-1 test vectors of RFC 6238, appendix B, truncated to 6 digits
+    let totp = Totp::new(b"12345678901234567890".to_vec());
+    assert_eq!(totp.code(Totp::step_at(59)), "287082");
+    assert_eq!(totp.code(Totp::step_at(1111111109)), "081804");
+    assert_eq!(totp.code(Totp::step_at(1234567890)), "005924");
+    assert_eq!(totp.code(Totp::step_at(2000000000)), "279037");
+
+    let step = Totp::step_at(1111111109);
+    assert_eq!(totp.verify("081 804", 1111111109, 0), Some(step));
+    assert_eq!(totp.verify("081804", 1111111109 + 30, 0), Some(step));
+    assert_eq!(totp.verify("081804", 1111111109 + 90, 0), None);
+    // a code that was already used is refused
+    assert_eq!(totp.verify("081804", 1111111109, step), None);
+    assert_eq!(totp.verify("81804", 1111111109, 0), None);
+
+    assert_eq!(totp.secret_base32(), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+    assert_eq!(
+        totp.uri("Euclidon Wiki", "euclid"),
+        "otpauth://totp/Euclidon%20Wiki:euclid?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+         &issuer=Euclidon%20Wiki&algorithm=SHA1&digits=6&period=30"
+    );
+}
+
+#[test]
+fn recovery_code_test() {
+    let code = generate_recovery_code();
+    assert_eq!(code.len(), 11);
+    assert_eq!(normalize_recovery_code(&code.to_uppercase()), code);
+    assert_eq!(normalize_recovery_code(" K3D9X MQ2PA "), "k3d9x-mq2pa");
+}
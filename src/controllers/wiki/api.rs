@@ -0,0 +1,62 @@
+use axum::{
+    debug_handler,
+    extract::Query,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{model::page::Page, title::PageTitle, AppState, Error};
+
+/// The most titles returned by a single suggestion request.
+const MAX_SUGGESTIONS: i64 = 50;
+
+#[derive(Deserialize)]
+pub struct SuggestQuery {
+    #[serde(default)]
+    search: String,
+    #[serde(default = "SuggestQuery::default_limit")]
+    limit: i64,
+}
+
+impl SuggestQuery {
+    fn default_limit() -> i64 {
+        10
+    }
+}
+
+/// Suggests existing titles starting with the searched text, ignoring case, for autocompletion.
+#[debug_handler(state = AppState)]
+pub async fn suggest(
+    AppState(app): AppState,
+    Query(query): Query<SuggestQuery>,
+) -> Result<Response, Error> {
+    let titles = match PageTitle::parse(&query.search, &app.config.titles) {
+        Ok(prefix) if !prefix.namespace.is_special() => {
+            let conn = &mut app.db.pool.get()?;
+            Page::suggest(
+                prefix.namespace,
+                &prefix.key(),
+                query.limit.clamp(1, MAX_SUGGESTIONS),
+                conn,
+            )?
+            .iter()
+            .map(|title| {
+                let title = PageTitle::from_stored(prefix.namespace, title);
+                json!({
+                    "display": title.display(),
+                    "query": title.query(),
+                })
+            })
+            .collect::<Vec<_>>()
+        }
+        _ => Vec::new(),
+    };
+
+    Ok(Json(json!({
+        "search": query.search,
+        "titles": titles,
+    }))
+    .into_response())
+}
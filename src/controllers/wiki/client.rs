@@ -0,0 +1,43 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, StatusCode},
+};
+
+use crate::AppState;
+
+/// The address of the client making a request, for throttling by address.
+///
+/// Taken from the configured header when the wiki runs behind a reverse proxy, in which case the
+/// last address in the header is used, as it is the one added by the proxy itself. Falls back to
+/// the address of the connecting peer.
+pub struct ClientAddress(pub String);
+
+impl FromRequestParts<AppState> for ClientAddress {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let forwarded = state
+            .config
+            .client_ip_header
+            .as_deref()
+            .and_then(|header| parts.headers.get_all(header).iter().next_back())
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .map(str::trim)
+            .filter(|address| !address.is_empty());
+        if let Some(address) = forwarded {
+            return Ok(Self(address.to_string()));
+        }
+
+        parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| Self(address.ip().to_string()))
+            .ok_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
@@ -0,0 +1,153 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, Request},
+    http::StatusCode,
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use base64::{prelude::BASE64_URL_SAFE, Engine};
+use hmac::{Hmac, Mac};
+use rand_chacha::ChaCha12Rng;
+use rand_core::{RngCore, SeedableRng};
+use serde::Deserialize;
+use sha3::Sha3_256;
+use tera::Context;
+
+use crate::{controllers::wiki::login::SESSION_COOKIE, model::user::SessionConfig, AppState};
+
+/// The name of the cookie tying CSRF tokens to a browser before it logs in.
+pub(crate) const CSRF_COOKIE: &str = "euc-csrf";
+
+tokio::task_local! {
+    static TOKEN: String;
+}
+
+/// Returns the CSRF token for forms rendered during the current request, if any.
+pub(crate) fn current_token() -> Option<String> {
+    TOKEN.try_with(Clone::clone).ok()
+}
+
+/// Middleware making the CSRF token of the request available to templates.
+///
+/// The token is derived from the session cookie, or from a random pre-session cookie for visitors
+/// that are not logged in, which is set here if missing.
+pub async fn provide_token(
+    AppState(app): AppState,
+    mut jar: CookieJar,
+    request: Request,
+    next: Next,
+) -> (CookieJar, Response) {
+    let config = &app.config.sessions;
+    let base = match jar.get(SESSION_COOKIE).or_else(|| jar.get(CSRF_COOKIE)) {
+        Some(cookie) => cookie.value().to_string(),
+        None => {
+            let value = random_value();
+            jar = jar.add(csrf_cookie(config, value.clone()));
+            value
+        }
+    };
+
+    let response = TOKEN
+        .scope(derive_token(config, &base), next.run(request))
+        .await;
+
+    (jar, response)
+}
+
+/// Extractor verifying the CSRF token of a form before passing the request on to `F`.
+///
+/// Requests without a matching `csrf_token` field are rejected with a 403 page.
+pub struct Csrf<F>(pub F);
+
+impl<F> FromRequest<AppState> for Csrf<F>
+where
+    F: FromRequest<AppState>,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &AppState) -> Result<Self, Self::Rejection> {
+        let (parts, body) = request.into_parts();
+        let jar = CookieJar::from_headers(&parts.headers);
+        let bytes = Bytes::from_request(Request::from_parts(parts.clone(), body), state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let data: CsrfData = serde_html_form::from_bytes(&bytes).unwrap_or_default();
+        let valid = match (
+            jar.get(SESSION_COOKIE).or_else(|| jar.get(CSRF_COOKIE)),
+            data.csrf_token
+                .and_then(|token| BASE64_URL_SAFE.decode(token).ok()),
+        ) {
+            (Some(cookie), Some(token)) => token_mac(&state.config.sessions, cookie.value())
+                .verify_slice(&token)
+                .is_ok(),
+            _ => false,
+        };
+        if !valid {
+            return Err(rejection(state));
+        }
+
+        F::from_request(Request::from_parts(parts, Body::from(bytes)), state)
+            .await
+            .map(Self)
+            .map_err(IntoResponse::into_response)
+    }
+}
+
+#[derive(Default, Deserialize)]
+struct CsrfData {
+    #[serde(default)]
+    csrf_token: Option<String>,
+}
+
+fn rejection(app: &AppState) -> Response {
+    match app.renderer.render("csrf", &Context::new()) {
+        Ok(body) => (StatusCode::FORBIDDEN, Html::from(body)).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Keys the token on the cookie it is tied to, so that it cannot be guessed by other sites.
+fn token_mac(config: &SessionConfig, base: &str) -> Hmac<Sha3_256> {
+    let mut mac = Hmac::<Sha3_256>::new_from_slice(&config.key)
+        .expect("HMAC should accept keys of any length");
+    mac.update(b"csrf:");
+    mac.update(base.as_bytes());
+    mac
+}
+
+fn derive_token(config: &SessionConfig, base: &str) -> String {
+    BASE64_URL_SAFE.encode(token_mac(config, base).finalize().into_bytes())
+}
+
+fn random_value() -> String {
+    let mut value = [0; 32];
+    ChaCha12Rng::from_os_rng().fill_bytes(&mut value);
+    BASE64_URL_SAFE.encode(value)
+}
+
+fn csrf_cookie(config: &SessionConfig, value: String) -> Cookie<'static> {
+    Cookie::build((CSRF_COOKIE, value))
+        .path("/w")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(config.secure_cookie)
+        .build()
+}
+
+#[test]
+fn csrf_token_test() {
+    let config = SessionConfig::default();
+    let token = derive_token(&config, "session");
+    let bytes = BASE64_URL_SAFE.decode(&token).unwrap();
+
+    assert!(token_mac(&config, "session").verify_slice(&bytes).is_ok());
+    assert!(token_mac(&config, "other").verify_slice(&bytes).is_err());
+
+    let other = SessionConfig::default();
+    assert!(token_mac(&other, "session").verify_slice(&bytes).is_err());
+}
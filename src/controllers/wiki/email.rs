@@ -0,0 +1,236 @@
+use axum::{
+    debug_handler,
+    extract::Query,
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    Form,
+};
+use chrono::Utc;
+use diesel::{connection::LoadConnection, pg::Pg, Connection};
+use serde::Deserialize;
+use serde_json::json;
+use tera::Context;
+
+use crate::{
+    controllers::wiki::{
+        csrf::Csrf,
+        signup::is_valid_email,
+        viewer::{LoggedIn, Viewer},
+    },
+    model::user::{EmailVerification, User},
+    App, AppState, Error,
+};
+
+#[debug_handler(state = AppState)]
+pub async fn get(
+    AppState(app): AppState,
+    viewer: Viewer,
+    Query(query): Query<EmailQuery>,
+) -> Result<Response, Error> {
+    if let Some(token) = query.token {
+        let conn = &mut app.db.pool.get()?;
+        // following the link is enough, so that it also works in another browser
+        let verified = verify_token(&app, &token, conn)?;
+        let stage = if verified {
+            EmailStage::Verified
+        } else {
+            EmailStage::Invalid
+        };
+
+        return render_email(&app, stage, None, None);
+    }
+
+    let Some(user) = viewer.user else {
+        return Ok(Redirect::to("/w/login").into_response());
+    };
+
+    render_email(&app, EmailStage::Show, None, Some(&user))
+}
+
+#[debug_handler(state = AppState)]
+pub async fn post(
+    AppState(app): AppState,
+    LoggedIn { user, .. }: LoggedIn,
+    Csrf(Form(data)): Csrf<Form<EmailData>>,
+) -> Result<Response, Error> {
+    let conn = &mut app.db.pool.get()?;
+
+    if data.resend.is_some() {
+        if user.is_email_verified() {
+            return render_email(&app, EmailStage::Show, None, Some(&user));
+        }
+        send_verification(&app, &user, &user.email, conn)?;
+        return render_email(&app, EmailStage::Sent, None, Some(&user));
+    }
+
+    let email = data.email.as_deref().unwrap_or_default().trim();
+    let password = data.password.as_deref().unwrap_or_default();
+    let result = if !user
+        .password
+        .compare(password, &app.config.passwords)
+        .unwrap_or(false)
+    {
+        Err(EmailError::WrongPassword)
+    } else if !is_valid_email(email) {
+        Err(EmailError::InvalidEmail)
+    } else if email == user.email {
+        Err(EmailError::Unchanged)
+    } else if User::by_email(email, conn)?.is_some() {
+        Err(EmailError::Conflict)
+    } else {
+        Ok(())
+    };
+    if let Err(error) = result {
+        return render_email(&app, EmailStage::Show, Some(error), Some(&user));
+    }
+
+    send_verification(&app, &user, email, conn)?;
+
+    render_email(&app, EmailStage::Sent, None, Some(&user))
+}
+
+/// Sends a link for verifying `email` to that address, which becomes the address of `user` once
+/// the link is followed.
+pub(crate) fn send_verification<C>(
+    app: &App,
+    user: &User,
+    email: &str,
+    conn: &mut C,
+) -> Result<(), Error>
+where
+    C: Connection<Backend = Pg>,
+{
+    let token =
+        EmailVerification::generate(user.id, email, &app.config.sessions, Utc::now(), conn)?;
+    app.config.mail.send(
+        email,
+        &format!("Verify your e-mail address on {}", app.config.title),
+        &format!(
+            "Someone, hopefully you, entered this address for the account {} on {}.\n\n\
+             To confirm that it is yours, open this link within {} days:\n\n\
+             {}/w/email?token={}\n\n\
+             If you did not ask for this, you can ignore this message.",
+            user.name,
+            app.config.title,
+            EmailVerification::LIFETIME.num_days(),
+            app.config.public_url,
+            token,
+        ),
+    )?;
+
+    Ok(())
+}
+
+/// Redeems the verification for `token`, returning whether the address was verified.
+fn verify_token<C>(app: &App, token: &str, conn: &mut C) -> Result<bool, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let now = Utc::now();
+    let Some(verification) = EmailVerification::from_token(token, &app.config.sessions, now, conn)?
+    else {
+        return Ok(false);
+    };
+
+    conn.transaction(|conn| {
+        if !verification.redeem(conn)? {
+            return Ok(false);
+        }
+        let Some(user) = User::by_id(verification.user_id, conn)? else {
+            return Ok(false);
+        };
+        // the address may have been taken by another account in the meantime
+        if User::by_email(&verification.email, conn)?.is_some_and(|other| other.id != user.id) {
+            return Ok(false);
+        }
+
+        user.verify_email(&verification.email, now, conn)
+    })
+}
+
+fn render_email(
+    app: &App,
+    stage: EmailStage,
+    error: Option<EmailError>,
+    user: Option<&User>,
+) -> Result<Response, Error> {
+    let status = match (&stage, &error) {
+        (_, Some(error)) => error.status(),
+        (EmailStage::Invalid, None) => StatusCode::NOT_FOUND,
+        _ => StatusCode::OK,
+    };
+    let html = app.renderer.render(
+        "email",
+        &Context::from_serialize(json!({
+            "stage": stage.name(),
+            "error": error.as_ref().map(ToString::to_string),
+            "email": user.map(|user| &user.email),
+            "verified": user.is_some_and(User::is_email_verified),
+        }))?,
+    )?;
+
+    Ok((status, Html::from(html)).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct EmailQuery {
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Either a new address along with the current password, or a request to send the verification
+/// of the current address again.
+#[derive(Deserialize)]
+pub struct EmailData {
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub resend: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EmailStage {
+    /// Showing the current address, with a form to change it.
+    Show,
+    /// Telling that a verification link was sent.
+    Sent,
+    /// Telling that the address was verified.
+    Verified,
+    /// Telling that the link has expired or was already used.
+    Invalid,
+}
+
+impl EmailStage {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Sent => "sent",
+            Self::Verified => "verified",
+            Self::Invalid => "invalid",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error("the password is wrong")]
+    WrongPassword,
+    #[error("the e-mail address is not valid")]
+    InvalidEmail,
+    #[error("this is already your e-mail address")]
+    Unchanged,
+    #[error("the e-mail address is already in use")]
+    Conflict,
+}
+
+impl EmailError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::WrongPassword => StatusCode::FORBIDDEN,
+            Self::Conflict => StatusCode::CONFLICT,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use axum::{
+    debug_handler,
+    extract::Query,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use chrono::Utc;
+use diesel::{connection::LoadConnection, pg::Pg, Connection};
+use serde::Deserialize;
+use serde_json::json;
+use tera::Context;
+
+use crate::{
+    auth::Permission,
+    controllers::wiki::{csrf::Csrf, viewer::LoggedIn},
+    model::{group::UserGroup, user::User},
+    App, AppState, Error,
+};
+
+#[debug_handler(state = AppState)]
+pub async fn get(
+    AppState(app): AppState,
+    LoggedIn { permissions, .. }: LoggedIn,
+    Query(query): Query<GroupsQuery>,
+) -> Result<Response, Error> {
+    if !permissions.has(Permission::Groups) {
+        return render_groups(&app, None, Some(GroupsError::NotPermitted), None);
+    }
+    let Some(name) = query.user.filter(|name| !name.trim().is_empty()) else {
+        return render_groups(&app, None, None, None);
+    };
+
+    let conn = &mut app.db.pool.get()?;
+    let Some(user) = User::by_name(name.trim(), conn)? else {
+        return render_groups(&app, None, Some(GroupsError::UnknownUser), None);
+    };
+    render_groups(&app, Some(member(&user, conn)?), None, None)
+}
+
+#[debug_handler(state = AppState)]
+pub async fn post(
+    AppState(app): AppState,
+    LoggedIn { permissions, .. }: LoggedIn,
+    Csrf(Form(data)): Csrf<Form<GroupsData>>,
+) -> Result<Response, Error> {
+    if !permissions.has(Permission::Groups) {
+        return render_groups(&app, None, Some(GroupsError::NotPermitted), None);
+    }
+
+    let conn = &mut app.db.pool.get()?;
+    let Some(user) = User::by_name(data.user.trim(), conn)? else {
+        return render_groups(&app, None, Some(GroupsError::UnknownUser), None);
+    };
+    // groups without configured permissions would grant nothing, and are most likely typos
+    if !app
+        .config
+        .permissions
+        .assignable()
+        .contains(&data.group.as_str())
+    {
+        return render_groups(
+            &app,
+            Some(member(&user, conn)?),
+            Some(GroupsError::UnknownGroup),
+            None,
+        );
+    }
+
+    let changed = if data.remove.is_some() {
+        UserGroup::remove(user.id, &data.group, conn)?
+    } else {
+        UserGroup::add(user.id, &data.group, Utc::now(), conn)?
+    };
+    let changed = changed.then_some(data.group.as_str());
+    render_groups(&app, Some(member(&user, conn)?), None, changed)
+}
+
+/// Renders the form for looking up a user, along with the groups of the user if one was found.
+fn render_groups(
+    app: &App,
+    member: Option<serde_json::Value>,
+    error: Option<GroupsError>,
+    changed: Option<&str>,
+) -> Result<Response, Error> {
+    let status = match &error {
+        Some(GroupsError::NotPermitted) => StatusCode::FORBIDDEN,
+        Some(GroupsError::UnknownUser | GroupsError::UnknownGroup) => StatusCode::BAD_REQUEST,
+        None => StatusCode::OK,
+    };
+
+    let html = app.renderer.render(
+        "groups",
+        &Context::from_serialize(json!({
+            "permitted": !matches!(error, Some(GroupsError::NotPermitted)),
+            "error": error.as_ref().map(ToString::to_string),
+            "member": member,
+            "assignable": app.config.permissions.assignable(),
+            "changed": changed,
+        }))?,
+    )?;
+
+    Ok((status, Html::from(html)).into_response())
+}
+
+/// Returns `user` along with the groups they are in, as passed into the template.
+fn member<C>(user: &User, conn: &mut C) -> Result<serde_json::Value, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    Ok(json!({
+        "name": user.name,
+        "groups": UserGroup::names_by_user_id(user.id, conn)?,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct GroupsQuery {
+    #[serde(default)]
+    pub user: Option<String>,
+}
+
+/// A user to add to a group, or to remove from it if `remove` is set.
+#[derive(Deserialize)]
+pub struct GroupsData {
+    pub user: String,
+    pub group: String,
+    #[serde(default)]
+    pub remove: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum GroupsError {
+    #[error("you do not have permission to manage groups")]
+    NotPermitted,
+    #[error("there is no user with this name")]
+    UnknownUser,
+    #[error("there is no group with this name")]
+    UnknownGroup,
+}
@@ -1,59 +1,109 @@
 use axum::{
     debug_handler,
+    extract::Query,
     response::{Html, IntoResponse, Redirect, Response},
     Form,
 };
-use axum_extra::extract::{cookie::Cookie, CookieJar};
-use chrono::Utc;
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use base64::{prelude::BASE64_URL_SAFE, Engine};
+use chrono::{DateTime, TimeDelta, Utc};
+use cookie::CookieBuilder;
 use diesel::{connection::LoadConnection, pg::Pg, Connection};
+use hmac::{Hmac, Mac};
 use serde::Deserialize;
 use serde_json::json;
+use sha3::Sha3_256;
 use tera::Context;
 
 use crate::{
-    model::user::{Session, User},
-    AppState, Error,
+    auth::Password,
+    controllers::wiki::{client::ClientAddress, csrf::Csrf, viewer::Viewer},
+    model::{
+        login::LoginAttempt,
+        totp::{RecoveryCode, UserTotp},
+        user::{Session, SessionConfig, User},
+    },
+    App, AppState, Error,
 };
 
 #[debug_handler(state = AppState)]
-pub async fn get(AppState(app): AppState) -> Result<Response, Error> {
-    Ok(Html::from(
-        Response::builder().body(
-            app.renderer
-                .render("login", &Context::from_serialize(json!({}))?)?,
-        )?,
-    )
+pub async fn get(
+    AppState(app): AppState,
+    viewer: Viewer,
+    Query(query): Query<LoginQuery>,
+) -> Result<Response, Error> {
+    Ok(Html::from(Response::builder().body(app.renderer.render(
+        "login",
+        &Context::from_serialize(json!({
+            "allow_registration": app.config.allow_registration,
+            "logged_in": viewer.session.is_some(),
+            "status": query.status,
+        }))?,
+    )?)?)
     .into_response())
 }
 
 #[debug_handler(state = AppState)]
 pub async fn post(
     AppState(app): AppState,
+    ClientAddress(address): ClientAddress,
+    viewer: Viewer,
     mut jar: CookieJar,
-    Form(data): Form<LoginData>,
+    Csrf(Form(data)): Csrf<Form<LoginData>>,
 ) -> Result<(CookieJar, Response), Error> {
-    let status = if jar.get("euc-user-token").is_none() {
+    let status = if viewer.session.is_none() {
         let conn = &mut app.db.pool.get()?;
-        if let Some(login) = data.load(conn)? {
-            match login.password.compare(&data.password) {
-                Ok(res) => {
-                    if res {
-                        let session = Session::generate(login.id, None, conn)?;
-                        jar = jar.add(Cookie::new("euc-user-token", session.token.clone()));
-
-                        _ = session.insert(conn)?;
-                        _ = login.mark_updated(Utc::now(), conn)?;
+        let now = Utc::now();
+
+        let login = data.load(conn)?;
+        let user_id = login.as_ref().map(|login| login.id);
+        if LoginAttempt::locked_until(user_id, &address, now, conn)?.is_some() {
+            // refused attempts are not recorded, so that guessing on does not extend the lockout
+            LoginStatus::Throttled
+        } else {
+            let success = match &login {
+                Some(login) if login.password.is_valid() => login
+                    .password
+                    .compare(&data.password, &app.config.passwords)?,
+                // unknown accounts take as long as known ones, so that timing does not tell
+                // whether an account exists
+                _ => {
+                    Password::compare_dummy(&data.password, &app.config.passwords)?;
+                    false
+                }
+            };
+            match (success, login) {
+                (true, Some(login)) => {
+                    let policy = &app.config.passwords;
+                    if login.password.needs_rehash(policy) {
+                        let password = Password::generate_current(&data.password, None, policy)?;
+                        _ = login.update_password(&password, conn)?;
                     }
 
-                    res.into()
+                    let remember = data.remember.is_some();
+                    if UserTotp::is_enabled_for(login.id, conn)? {
+                        // the login only counts as successful once the code is entered as well
+                        let pending = PendingLogin {
+                            user_id: login.id,
+                            remember,
+                            expire_on: now + PendingLogin::LIFETIME,
+                        };
+                        jar = jar.add(pending.cookie(&app.config.sessions));
+                        return Ok((jar, Redirect::to("/w/login/verify").into_response()));
+                    }
+
+                    _ = LoginAttempt::record(Some(login.id), &data.id, &address, true, now, conn)?;
+                    jar = start_session(&app, jar, &login, remember, now, conn)?;
+                    LoginStatus::Success
                 }
-                Err(_) => {
-                    login.set_invalid(conn)?;
+                _ => {
+                    _ = LoginAttempt::record(user_id, &data.id, &address, false, now, conn)?;
                     LoginStatus::Failure
                 }
             }
-        } else {
-            LoginStatus::Failure
         }
     } else {
         LoginStatus::Duplicate
@@ -65,10 +115,221 @@ pub async fn post(
     ))
 }
 
+#[debug_handler(state = AppState)]
+pub async fn get_verify(
+    AppState(app): AppState,
+    jar: CookieJar,
+    Query(query): Query<LoginQuery>,
+) -> Result<Response, Error> {
+    if PendingLogin::from_jar(&jar, &app.config.sessions, Utc::now()).is_none() {
+        return Ok(Redirect::to("/w/login").into_response());
+    }
+
+    Ok(Html::from(app.renderer.render(
+        "login/verify",
+        &Context::from_serialize(json!({
+            "status": query.status,
+        }))?,
+    )?)
+    .into_response())
+}
+
+#[debug_handler(state = AppState)]
+pub async fn post_verify(
+    AppState(app): AppState,
+    ClientAddress(address): ClientAddress,
+    mut jar: CookieJar,
+    Csrf(Form(data)): Csrf<Form<VerifyData>>,
+) -> Result<(CookieJar, Response), Error> {
+    let now = Utc::now();
+    let Some(pending) = PendingLogin::from_jar(&jar, &app.config.sessions, now) else {
+        return Ok((jar, Redirect::to("/w/login").into_response()));
+    };
+
+    let conn = &mut app.db.pool.get()?;
+    let Some(user) = User::by_id(pending.user_id, conn)? else {
+        return Ok((jar, Redirect::to("/w/login").into_response()));
+    };
+
+    let status = if LoginAttempt::locked_until(Some(user.id), &address, now, conn)?.is_some() {
+        LoginStatus::Throttled
+    } else {
+        let success = match UserTotp::by_user_id(user.id, conn)? {
+            Some(totp) if totp.is_enabled() => {
+                totp.verify(&data.code, now, conn)?
+                    || RecoveryCode::redeem(user.id, &data.code, &app.config.passwords, now, conn)?
+            }
+            // the code was disabled in the meantime, so the password is enough
+            _ => true,
+        };
+        _ = LoginAttempt::record(Some(user.id), &user.name, &address, success, now, conn)?;
+
+        if success {
+            jar = jar.remove(PendingLogin::removal_cookie(&app.config.sessions));
+            jar = start_session(&app, jar, &user, pending.remember, now, conn)?;
+            return Ok((
+                jar,
+                Redirect::to(&format!("/w/login?{}", LoginStatus::Success.into_query()))
+                    .into_response(),
+            ));
+        }
+
+        LoginStatus::Failure
+    };
+
+    Ok((
+        jar,
+        Redirect::to(&format!("/w/login/verify?{}", status.into_query())).into_response(),
+    ))
+}
+
+/// Creates a session for `user` and adds its cookie to `jar`.
+fn start_session<C>(
+    app: &App,
+    jar: CookieJar,
+    user: &User,
+    remember: bool,
+    now: DateTime<Utc>,
+    conn: &mut C,
+) -> Result<CookieJar, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let config = &app.config.sessions;
+    let (session, token) = Session::generate(user.id, remember, config, now, conn)?;
+    let jar = jar.add(session_cookie(config, &session, &token, now));
+
+    _ = session.insert(conn)?;
+    _ = user.mark_updated(now, conn)?;
+
+    Ok(jar)
+}
+
+/// The name of the cookie holding the session token.
+pub(crate) const SESSION_COOKIE: &str = "euc-user-token";
+
+/// Builds the cookie holding the token of `session`, expiring along with the session.
+///
+/// The cookie is hidden from scripts and not sent along with requests from other sites, and is
+/// only sent over HTTPS unless disabled for development.
+pub(crate) fn session_cookie(
+    config: &SessionConfig,
+    session: &Session,
+    token: &str,
+    now: DateTime<Utc>,
+) -> Cookie<'static> {
+    let mut cookie = base_cookie(config, token.to_string());
+    if let Some(expire_on) = session.expire_on {
+        cookie = cookie.max_age(cookie::time::Duration::seconds(
+            (expire_on - now).num_seconds().max(0),
+        ));
+    }
+
+    cookie.build()
+}
+
+/// Builds a cookie removing the session cookie from the browser.
+pub(crate) fn removal_cookie(config: &SessionConfig) -> Cookie<'static> {
+    base_cookie(config, String::new()).build()
+}
+
+fn base_cookie(config: &SessionConfig, value: String) -> CookieBuilder<'static> {
+    Cookie::build((SESSION_COOKIE, value))
+        .path("/w")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(config.secure_cookie)
+}
+
+/// A login whose password was right, waiting for the code of the second step.
+///
+/// It is kept in a cookie signed with the session key, so that it cannot be forged to skip the
+/// password.
+struct PendingLogin {
+    user_id: i64,
+    remember: bool,
+    expire_on: DateTime<Utc>,
+}
+
+impl PendingLogin {
+    /// The name of the cookie holding the pending login.
+    const COOKIE: &str = "euc-login-pending";
+    /// How long the code can be entered after the password.
+    const LIFETIME: TimeDelta = TimeDelta::minutes(5);
+
+    fn from_jar(jar: &CookieJar, config: &SessionConfig, now: DateTime<Utc>) -> Option<Self> {
+        let (payload, mac) = jar.get(Self::COOKIE)?.value().rsplit_once('.')?;
+        Self::mac(config, payload)
+            .verify_slice(&BASE64_URL_SAFE.decode(mac).ok()?)
+            .ok()?;
+
+        let mut fields = payload.split('.');
+        let pending = Self {
+            user_id: fields.next()?.parse().ok()?,
+            remember: fields.next()? == "1",
+            expire_on: DateTime::from_timestamp(fields.next()?.parse().ok()?, 0)?,
+        };
+
+        (now < pending.expire_on).then_some(pending)
+    }
+
+    fn cookie(&self, config: &SessionConfig) -> Cookie<'static> {
+        let payload = format!(
+            "{}.{}.{}",
+            self.user_id,
+            u8::from(self.remember),
+            self.expire_on.timestamp()
+        );
+        let mac = BASE64_URL_SAFE.encode(Self::mac(config, &payload).finalize().into_bytes());
+
+        Self::base_cookie(config, format!("{payload}.{mac}"))
+            .max_age(cookie::time::Duration::seconds(
+                Self::LIFETIME.num_seconds(),
+            ))
+            .build()
+    }
+
+    fn removal_cookie(config: &SessionConfig) -> Cookie<'static> {
+        Self::base_cookie(config, String::new()).build()
+    }
+
+    fn base_cookie(config: &SessionConfig, value: String) -> CookieBuilder<'static> {
+        Cookie::build((Self::COOKIE, value))
+            .path("/w/login")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .secure(config.secure_cookie)
+    }
+
+    fn mac(config: &SessionConfig, payload: &str) -> Hmac<Sha3_256> {
+        let mut mac = Hmac::<Sha3_256>::new_from_slice(&config.key)
+            .expect("HMAC should accept keys of any length");
+        mac.update(b"login:");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    /// The outcome of the previous attempt, as set by `LoginStatus::into_query`.
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct LoginData {
     pub id: String,
     pub password: String,
+    /// Set to stay logged in for longer, selecting the remembered session lifetimes.
+    #[serde(default)]
+    pub remember: Option<String>,
+}
+
+/// The code of the second login step, either from the authenticator or a recovery code.
+#[derive(Deserialize)]
+pub struct VerifyData {
+    pub code: String,
 }
 
 impl LoginData {
@@ -101,6 +362,8 @@ pub enum LoginKind {
 pub enum LoginStatus {
     Failure,
     Duplicate,
+    /// Refused because of too many failed attempts for the account or from the address.
+    Throttled,
     Success,
 }
 
@@ -116,12 +379,17 @@ impl std::fmt::Display for LoginStatus {
             Self::Failure => write!(f, "failure"),
             Self::Success => write!(f, "success"),
             Self::Duplicate => write!(f, "duplicate"),
+            Self::Throttled => write!(f, "throttled"),
         }
     }
 }
 
 impl From<bool> for LoginStatus {
     fn from(value: bool) -> Self {
-        value.then_some(Self::Success).unwrap_or(Self::Failure)
+        if value {
+            Self::Success
+        } else {
+            Self::Failure
+        }
     }
 }
@@ -0,0 +1,53 @@
+use axum::{
+    debug_handler,
+    response::{Html, IntoResponse, Redirect, Response},
+    Form,
+};
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+use serde_json::json;
+use tera::Context;
+
+use crate::{
+    controllers::wiki::{csrf::Csrf, login::removal_cookie, viewer::Viewer},
+    model::user::Session,
+    AppState, Error,
+};
+
+#[debug_handler(state = AppState)]
+pub async fn get(AppState(app): AppState, viewer: Viewer) -> Result<Response, Error> {
+    Ok(Html::from(app.renderer.render(
+        "logout",
+        &Context::from_serialize(json!({
+            "logged_in": viewer.session.is_some(),
+        }))?,
+    )?)
+    .into_response())
+}
+
+#[debug_handler(state = AppState)]
+pub async fn post(
+    AppState(app): AppState,
+    viewer: Viewer,
+    mut jar: CookieJar,
+    Csrf(Form(data)): Csrf<Form<LogoutData>>,
+) -> Result<(CookieJar, Response), Error> {
+    let conn = &mut app.db.pool.get()?;
+    if let Some(session) = viewer.session {
+        if data.everywhere.is_some() {
+            _ = Session::delete_all_by_user_id(session.user_id, conn)?;
+        } else {
+            _ = session.delete(conn)?;
+        }
+    }
+    jar = jar.remove(removal_cookie(&app.config.sessions));
+
+    Ok((jar, Redirect::to("/w/").into_response()))
+}
+
+#[derive(Deserialize)]
+pub struct LogoutData {
+    /// Set to end all sessions of the user, rather than only the current one.
+    #[serde(default)]
+    pub everywhere: Option<String>,
+}
@@ -1,2 +1,14 @@
+pub mod api;
+pub mod client;
+pub mod csrf;
+pub mod email;
+pub mod groups;
 pub mod login;
+pub mod logout;
 pub mod page;
+pub mod password;
+pub mod reset;
+pub mod signup;
+pub mod special;
+pub mod totp;
+pub mod viewer;
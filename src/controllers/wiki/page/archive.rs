@@ -0,0 +1,192 @@
+use axum::{
+    extract::OriginalUri,
+    response::{IntoResponse, Redirect, Response},
+};
+use diesel::{connection::LoadConnection, pg::Pg, Connection};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{
+    page_content, page_not_found, permission_denied, redirect_to_login, render_page,
+    update_derived, ActionKind, RedirectQuery, DATE_FORMAT,
+};
+use crate::{
+    auth::Permission,
+    controllers::wiki::viewer::Viewer,
+    model::{
+        archive::{ArchivedPage, ArchivedRevision},
+        page::Page,
+        user::User,
+    },
+    title::PageTitle,
+    App, Error,
+};
+
+#[derive(Deserialize)]
+pub struct DeletePage {
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Deserialize)]
+pub struct UndeletePage {
+    /// The ids of the archived revisions to restore; if empty, all of them are restored.
+    #[serde(default)]
+    pub revision: Vec<i64>,
+}
+
+pub(super) fn view_delete<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    uri: OriginalUri,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    if viewer.session.is_none() {
+        return Ok(redirect_to_login(&uri, ActionKind::Delete));
+    }
+    if !viewer.can(Permission::Delete) {
+        return permission_denied(app, title, ActionKind::Delete);
+    }
+    if Page::by_title(title.namespace, &title.key(), conn)?.is_none() {
+        return page_not_found(app, title, RedirectQuery::default(), conn);
+    }
+
+    render_page(
+        app,
+        "page/delete",
+        json!({
+            "title": title.to_json(),
+        }),
+    )
+}
+
+pub(super) fn submit_delete<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    delete: DeletePage,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let Some(user) = viewer.user_with(Permission::Delete) else {
+        return permission_denied(app, title, ActionKind::Delete);
+    };
+    let Some(page) = Page::by_title(title.namespace, &title.key(), conn)? else {
+        return page_not_found(app, title, RedirectQuery::default(), conn);
+    };
+
+    ArchivedPage::archive(page, user.id, delete.reason.trim(), conn)?;
+    Ok(Redirect::to(&format!("/w/page/{}", title.query())).into_response())
+}
+
+pub(super) fn view_undelete<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    uri: OriginalUri,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    if viewer.session.is_none() {
+        return Ok(redirect_to_login(&uri, ActionKind::Undelete));
+    }
+    if !viewer.can(Permission::Delete) {
+        return permission_denied(app, title, ActionKind::Undelete);
+    }
+
+    let revisions = ArchivedRevision::by_title(title.namespace, &title.key(), conn)?
+        .into_iter()
+        .map(|(revision, user_name)| {
+            json!({
+                "id": revision.id,
+                "user": user_name,
+                "created_on": revision.created_on.format(DATE_FORMAT).to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    render_page(
+        app,
+        "page/undelete",
+        json!({
+            "title": title.to_json(),
+            "exists": Page::by_title(title.namespace, &title.key(), conn)?.is_some(),
+            "revisions": revisions,
+        }),
+    )
+}
+
+pub(super) fn submit_undelete<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    undelete: UndeletePage,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    if !viewer.can(Permission::Delete) {
+        return permission_denied(app, title, ActionKind::Undelete);
+    }
+
+    let ids = if undelete.revision.is_empty() {
+        ArchivedRevision::by_title(title.namespace, &title.key(), conn)?
+            .into_iter()
+            .map(|(revision, _)| revision.id)
+            .collect()
+    } else {
+        undelete.revision
+    };
+
+    Ok(
+        match ArchivedPage::restore(title.namespace, &title.key(), &ids, conn)? {
+            Some(page) => {
+                if let Some(body) = page_content(Some(&page), conn)? {
+                    update_derived(&app.config.titles, &page, &body, conn)?;
+                }
+                Redirect::to(&format!("/w/page/{}", title.query())).into_response()
+            }
+            None => {
+                Redirect::to(&format!("/w/page/{}?action=undelete", title.query())).into_response()
+            }
+        },
+    )
+}
+
+/// Shows the deletion notice in place of a page which has been deleted.
+pub(super) fn view_deleted<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    archive: ArchivedPage,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let deleted_by = User::by_id(archive.deleted_by, conn)?.map(|user| user.name);
+    let can_undelete = viewer.user_with(Permission::Delete).is_some();
+
+    render_page(
+        app,
+        "page/deleted",
+        json!({
+            "title": title.to_json(),
+            "deletion": {
+                "by": deleted_by,
+                "on": archive.deleted_on.format(DATE_FORMAT).to_string(),
+                "reason": archive.reason,
+            },
+            "can_undelete": can_undelete,
+        }),
+    )
+}
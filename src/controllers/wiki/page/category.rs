@@ -0,0 +1,97 @@
+use axum::response::Response;
+use diesel::{connection::LoadConnection, pg::Pg, Connection};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{page_categories, render_page};
+use crate::{
+    model::page::{Page, PageCategory},
+    title::PageTitle,
+    App, Error,
+};
+
+/// The number of pages, and separately of subcategories, listed at once on a category page.
+const CATEGORY_PAGE_SIZE: i64 = 200;
+
+/// Query parameters selecting which members of a category are listed.
+#[derive(Debug, Default, Deserialize)]
+pub struct CategoryPaging {
+    /// The number of pages to skip, in alphabetical order.
+    #[serde(default)]
+    pub offset: i64,
+    /// The number of subcategories to skip, in alphabetical order.
+    #[serde(default)]
+    pub suboffset: i64,
+}
+
+/// Shows a category page: its own content, if any, followed by its subcategories and pages.
+pub(super) fn view_category<C>(
+    app: &App,
+    title: &PageTitle,
+    page: Option<&Page>,
+    content: Option<String>,
+    paging: CategoryPaging,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let category = title.key();
+    let (offset, suboffset) = (paging.offset.max(0), paging.suboffset.max(0));
+
+    let subcategories =
+        PageCategory::members(&category, true, suboffset, CATEGORY_PAGE_SIZE, conn)?;
+    // the counts of this category and of all listed subcategories are fetched at once
+    let counts = PageCategory::count_members(
+        &std::iter::once(category.as_str())
+            .chain(subcategories.iter().map(|(_, key)| key.as_str()))
+            .collect::<Vec<_>>(),
+        conn,
+    )?;
+    let count = |key: &str| counts.get(key).copied().unwrap_or_default();
+
+    let subcategories = subcategories
+        .into_iter()
+        .map(|(namespace, key)| {
+            let subcategory = PageTitle::from_stored(namespace, &key);
+            let count = count(&key);
+            json!({
+                "name": subcategory.name,
+                "query": subcategory.query(),
+                "subcategories": count.subcategories,
+                "pages": count.pages,
+            })
+        })
+        .collect::<Vec<_>>();
+    let pages = PageCategory::members(&category, false, offset, CATEGORY_PAGE_SIZE, conn)?
+        .into_iter()
+        .map(|(namespace, key)| PageTitle::from_stored(namespace, &key).to_json())
+        .collect::<Vec<_>>();
+
+    render_page(
+        app,
+        "page/category",
+        json!({
+            "title": title.to_json(),
+            "name": title.name,
+            "content": content,
+            "categories": page_categories(page, conn)?,
+            "subcategories": listing(subcategories, suboffset, count(&category).subcategories),
+            "pages": listing(pages, offset, count(&category).pages),
+            "paging": {
+                "offset": offset,
+                "suboffset": suboffset,
+            },
+        }),
+    )
+}
+
+/// Describes one page of a listing of `total` entries starting at `offset`, for templates.
+fn listing(entries: Vec<serde_json::Value>, offset: i64, total: i64) -> serde_json::Value {
+    json!({
+        "entries": entries,
+        "total": total,
+        "prev": (offset > 0).then(|| (offset - CATEGORY_PAGE_SIZE).max(0)),
+        "next": (offset + CATEGORY_PAGE_SIZE < total).then_some(offset + CATEGORY_PAGE_SIZE),
+    })
+}
@@ -0,0 +1,782 @@
+mod archive;
+mod category;
+mod protect;
+mod talk;
+
+use axum::{
+    debug_handler,
+    extract::{OriginalUri, Path, Query, RawForm},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use chrono::Utc;
+use diesel::{connection::LoadConnection, pg::Pg, Connection};
+use serde::Deserialize;
+use serde_json::json;
+use tera::Context;
+
+use crate::{
+    auth::Permission,
+    controllers::wiki::{csrf::Csrf, special::view_special, viewer::Viewer},
+    model::{
+        archive::ArchivedPage,
+        namespace::Namespace,
+        page::{
+            Content, NewContent, NewPage, NewRevision, Page, PageCategory, PageLink, PageRedirect,
+            Revision,
+        },
+        protection::{ProtectedAction, Protection, ProtectionLevel},
+        search::{PageFormula, PageSearch},
+        user::User,
+    },
+    output::Body,
+    title::{encode_url, PageTitle, TitleConfig, TitleError, FORBIDDEN_CHARS, MAX_TITLE_LENGTH},
+    App, AppState, Error,
+};
+
+#[debug_handler(state = AppState)]
+pub async fn get(
+    AppState(app): AppState,
+    uri: OriginalUri,
+    viewer: Viewer,
+    Path(path): Path<String>,
+    Query(action): Query<Action>,
+    Query(redirect): Query<RedirectQuery>,
+    Query(paging): Query<category::CategoryPaging>,
+) -> Result<Response, Error> {
+    let title = match PageTitle::parse(&path, &app.config.titles) {
+        Ok(title) => title,
+        Err(error) => return bad_title(&app, &path, error),
+    };
+    // the path is decoded, so it is encoded again to compare it with the canonical form
+    if encode_url(&path) != title.query() {
+        Ok(
+            Redirect::permanent(&format!("/w/page/{}{}", title.query(), action.into_query()))
+                .into_response(),
+        )
+    } else if title.namespace.is_special() {
+        view_special(&app, &viewer, &title, &uri)
+    } else {
+        view_page(&app, &viewer, &title, uri, action, redirect, paging)
+    }
+}
+
+#[debug_handler(state = AppState)]
+pub async fn post(
+    AppState(app): AppState,
+    uri: OriginalUri,
+    viewer: Viewer,
+    Path(path): Path<String>,
+    Query(action): Query<Action>,
+    Csrf(RawForm(form)): Csrf<RawForm>,
+) -> Result<Response, Error> {
+    let title = match PageTitle::parse(&path, &app.config.titles) {
+        Ok(title) => title,
+        Err(error) => return bad_title(&app, &path, error),
+    };
+    if title.namespace.is_special() {
+        // special pages cannot be edited or otherwise acted upon
+        return Ok(Redirect::to(&format!("/w/page/{}", title.query())).into_response());
+    }
+
+    let conn = &mut app.db.pool.get()?;
+    if viewer.session.is_none() {
+        // TODO: redirecting to login like this will cause the form submission to be dropped,
+        // and so user contribution will probably be lost. Find some way to get around this.
+        return Ok(Redirect::to(&format!(
+            "/w/login?redirect_after={}&action={}",
+            uri.path(),
+            action.into_query(),
+        ))
+        .into_response());
+    }
+
+    let response = match action.kind {
+        Some(ActionKind::Delete) => archive::submit_delete(
+            &app,
+            &viewer,
+            &title,
+            serde_html_form::from_bytes(&form)?,
+            conn,
+        )?,
+        Some(ActionKind::Undelete) => archive::submit_undelete(
+            &app,
+            &viewer,
+            &title,
+            serde_html_form::from_bytes(&form)?,
+            conn,
+        )?,
+        Some(ActionKind::Protect) => protect::submit_protect(
+            &app,
+            &viewer,
+            &title,
+            serde_html_form::from_bytes(&form)?,
+            conn,
+        )?,
+        Some(ActionKind::NewThread) => talk::submit_new_thread(
+            &app,
+            &viewer,
+            &title,
+            serde_html_form::from_bytes(&form)?,
+            conn,
+        )?,
+        Some(ActionKind::Reply) => talk::submit_reply(
+            &app,
+            &viewer,
+            &title,
+            serde_html_form::from_bytes(&form)?,
+            conn,
+        )?,
+        _ => submit_edit(
+            &app,
+            &uri,
+            &viewer,
+            &title,
+            serde_html_form::from_bytes(&form)?,
+            conn,
+        )?,
+    };
+
+    Ok(response)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Action {
+    #[serde(default, rename = "action")]
+    pub kind: Option<ActionKind>,
+}
+
+impl Action {
+    pub fn into_query(self) -> String {
+        match self.kind {
+            Some(action) => format!("?action={action}"),
+            None => String::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionKind {
+    #[default]
+    View,
+    Edit,
+    Submit,
+    Delete,
+    Undelete,
+    Protect,
+    NewThread,
+    Reply,
+}
+
+impl ActionKind {
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            Self::View => "view",
+            Self::Submit => "submit",
+            Self::Edit => "edit",
+            Self::Delete => "delete",
+            Self::Undelete => "undelete",
+            Self::Protect => "protect",
+            Self::NewThread => "newthread",
+            Self::Reply => "reply",
+        }
+    }
+}
+
+pub(crate) const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Query parameters controlling how redirect pages are followed.
+#[derive(Debug, Default, Deserialize)]
+pub struct RedirectQuery {
+    /// Set to `no` to view a redirect page itself instead of following it.
+    #[serde(default)]
+    pub redirect: Option<String>,
+    /// The query title of the redirect page that led here, if any.
+    #[serde(default)]
+    pub rdfrom: Option<String>,
+}
+
+impl RedirectQuery {
+    /// Redirects are only followed one level deep, so arriving from a redirect never follows another.
+    pub fn should_follow(&self) -> bool {
+        self.redirect.as_deref() != Some("no") && self.rdfrom.is_none()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EditPage {
+    pub content: String,
+}
+
+impl std::fmt::Display for ActionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_text())
+    }
+}
+
+fn view_page(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    uri: OriginalUri,
+    action: Action,
+    redirect: RedirectQuery,
+    paging: category::CategoryPaging,
+) -> Result<Response, Error> {
+    let conn = &mut app.db.pool.get()?;
+    let page = Page::by_title(title.namespace, &title.key(), conn)?;
+
+    match action.kind {
+        Some(ActionKind::Edit) => view_page_editor(app, viewer, title, uri, page.as_ref(), conn),
+        Some(ActionKind::Delete) => archive::view_delete(app, viewer, title, uri, conn),
+        Some(ActionKind::Undelete) => archive::view_undelete(app, viewer, title, uri, conn),
+        Some(ActionKind::Protect) => protect::view_protect(app, viewer, title, uri, page, conn),
+        _ => match page_content(page.as_ref(), conn)? {
+            Some(Body::Redirect(target)) => {
+                if redirect.should_follow() {
+                    follow_redirect(app, title, &target)
+                } else {
+                    view_redirect_display(app, title, &target, redirect)
+                }
+            }
+            content if title.namespace.is_talk() => talk::view_talk(
+                app,
+                viewer,
+                title,
+                page.as_ref(),
+                content.map(Body::into_text),
+                redirect,
+                conn,
+            ),
+            content if title.namespace == Namespace::Category => category::view_category(
+                app,
+                title,
+                page.as_ref(),
+                content.map(Body::into_text),
+                paging,
+                conn,
+            ),
+            Some(content) => view_page_display(
+                app,
+                title,
+                content.into_text(),
+                redirect,
+                page.as_ref().map_or(Ok(Vec::new()), |page| {
+                    Protection::active_by_page_id(page.id, Utc::now(), conn)
+                })?,
+                page_categories(page.as_ref(), conn)?,
+                Page::subpage_titles(title.namespace, &title.key(), conn)?,
+            ),
+            None => match ArchivedPage::latest_by_title(title.namespace, &title.key(), conn)? {
+                Some(archive) => archive::view_deleted(app, viewer, title, archive, conn),
+                None => page_not_found(app, title, redirect, conn),
+            },
+        },
+    }
+}
+
+fn submit_edit<C>(
+    app: &App,
+    uri: &OriginalUri,
+    viewer: &Viewer,
+    title: &PageTitle,
+    edit: EditPage,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let existing = Page::by_title(title.namespace, &title.key(), conn)?;
+    let Some(user) = viewer.user_with(edit_permission(existing.as_ref())) else {
+        return permission_denied(app, title, ActionKind::Edit);
+    };
+    if !may_edit(app, viewer) {
+        return email_unverified(app, title, ActionKind::Edit);
+    }
+    if let Some(level) = check_protection(existing.as_ref(), ProtectedAction::Edit, viewer, conn)? {
+        return page_protected(app, title, ProtectedAction::Edit, level);
+    }
+
+    let body = Body::from_source(edit.content);
+
+    let content = NewContent::new(body).insert(conn)?;
+    let page = if let Some(mut page) = existing {
+        let revision =
+            NewRevision::new(Some(page.rev_id), content.id, user.id, None).insert(conn)?;
+        page.set_revision(&revision, conn)?;
+        page
+    } else {
+        let revision = NewRevision::new(None, content.id, user.id, None).insert(conn)?;
+        NewPage::new(
+            title.namespace,
+            &title.key(),
+            revision.id,
+            Some(revision.created_on),
+        )
+        .insert(conn)?
+    };
+    update_derived(&app.config.titles, &page, &content.body, conn)?;
+
+    Ok(Redirect::to(
+        &uri.path_and_query()
+            .map(|p| p.as_str().to_string())
+            .unwrap_or_else(|| format!("/w/page/{}", title.query())),
+    )
+    .into_response())
+}
+
+/// Updates the data derived from the current content of `page`, such as its redirect target, its
+/// categories, its outgoing links and its search index entries.
+///
+/// Called whenever the current revision of a page changes.
+fn update_derived<C>(
+    config: &TitleConfig,
+    page: &Page,
+    body: &Body,
+    conn: &mut C,
+) -> Result<(), Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let redirect_target = body
+        .as_redirect()
+        .and_then(|target| PageTitle::parse_link(split_fragment(target).0, config).ok());
+    PageRedirect::set(
+        page.id,
+        redirect_target
+            .as_ref()
+            .map(|target| (target.namespace, target.key()))
+            .as_ref()
+            .map(|(namespace, key)| (*namespace, key.as_str())),
+        conn,
+    )?;
+
+    let mut links = Vec::new();
+    let mut categories = Vec::new();
+    for target in body.links() {
+        // a leading colon links to a category page without adding the page to it
+        let (target, declares_category) = match target.strip_prefix(':') {
+            Some(target) => (target, false),
+            None => (target, body.as_redirect().is_none()),
+        };
+        let Ok(target) = PageTitle::parse_link(split_fragment(target).0, config) else {
+            continue;
+        };
+
+        if declares_category && target.namespace == Namespace::Category {
+            categories.push(target.key());
+        } else if !target.namespace.is_special() {
+            links.push((target.namespace, target.key()));
+        }
+    }
+    PageCategory::set(
+        page.id,
+        &categories.iter().map(String::as_str).collect::<Vec<_>>(),
+        conn,
+    )?;
+    PageLink::set(
+        page.id,
+        &links
+            .iter()
+            .map(|(namespace, key)| (*namespace, key.as_str()))
+            .collect::<Vec<_>>(),
+        conn,
+    )?;
+
+    let text = match body {
+        Body::Text(text) => Some(text.as_str()),
+        Body::Redirect(_) => None,
+    };
+    let title = PageTitle::from_stored(page.namespace, &page.title);
+    PageSearch::set(page.id, text.map(|text| (title.name.as_str(), text)), conn)?;
+    PageFormula::set(page.id, &body.formulas(), conn)?;
+
+    Ok(())
+}
+
+/// Lists the categories of `page` as passed into templates.
+fn page_categories<C>(page: Option<&Page>, conn: &mut C) -> Result<Vec<serde_json::Value>, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    Ok(match page {
+        Some(page) => PageCategory::by_page_id(page.id, conn)?
+            .into_iter()
+            .map(|category| {
+                let title = PageTitle::from_stored(Namespace::Category, &category);
+                json!({
+                    "name": title.name,
+                    "query": title.query(),
+                })
+            })
+            .collect(),
+        None => Vec::new(),
+    })
+}
+
+fn page_content<C>(page: Option<&Page>, conn: &mut C) -> Result<Option<Body>, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    Ok(if let Some(page) = page {
+        if let Some(revision) = Revision::by_id(page.rev_id, conn)? {
+            if let Some(content) = Content::by_id(revision.content_id, conn)? {
+                Some(content.body)
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    } else {
+        None
+    })
+}
+
+fn view_page_display(
+    app: &App,
+    title: &PageTitle,
+    content: String,
+    redirect: RedirectQuery,
+    protections: Vec<Protection>,
+    categories: Vec<serde_json::Value>,
+    subpages: Vec<String>,
+) -> Result<Response, Error> {
+    let protections = protections
+        .iter()
+        .map(|protection| {
+            json!({
+                "action": protection.action.as_text(),
+                "level": protection.level.name(),
+                "expire_on": protection
+                    .expire_on
+                    .map(|expire_on| expire_on.format(DATE_FORMAT).to_string()),
+                "reason": protection.reason,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    render_page(
+        app,
+        "page/view",
+        json!({
+            "title": title.to_json(),
+            "content": content,
+            "redirect": {
+                "from": redirected_from(&app.config.titles, &redirect),
+            },
+            "protections": protections,
+            "categories": categories,
+            "subpages": subpages
+                .iter()
+                .map(|subpage| PageTitle::from_stored(title.namespace, subpage).to_json())
+                .collect::<Vec<_>>(),
+        }),
+    )
+}
+
+/// The most similar titles suggested when a page is not found.
+const MAX_SIMILAR_TITLES: i64 = 10;
+
+fn page_not_found<C>(
+    app: &App,
+    title: &PageTitle,
+    redirect: RedirectQuery,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    // allow about one edit per three characters, so that short titles do not match everything
+    let max_distance = (title.name.chars().count() / 3).clamp(1, 3) as i32;
+    let similar = Page::similar(
+        title.namespace,
+        &title.key(),
+        max_distance,
+        MAX_SIMILAR_TITLES,
+        conn,
+    )?
+    .iter()
+    .map(|similar| PageTitle::from_stored(title.namespace, similar).to_json())
+    .collect::<Vec<_>>();
+
+    render_page(
+        app,
+        "page/not-found",
+        json!({
+            "title": title.to_json(),
+            "redirect": {
+                "from": redirected_from(&app.config.titles, &redirect),
+            },
+            "similar": similar,
+        }),
+    )
+}
+
+/// Explains why a requested title is invalid, instead of failing at the database.
+fn bad_title(app: &App, raw: &str, error: TitleError) -> Result<Response, Error> {
+    Ok((
+        StatusCode::BAD_REQUEST,
+        render_page(
+            app,
+            "page/bad-title",
+            json!({
+                "title": {
+                    "display": "Bad title",
+                },
+                "raw": raw,
+                "error": error.to_string(),
+                "forbidden": FORBIDDEN_CHARS.iter().collect::<String>(),
+                "max_length": MAX_TITLE_LENGTH,
+            }),
+        )?,
+    )
+        .into_response())
+}
+
+fn follow_redirect(app: &App, title: &PageTitle, target: &str) -> Result<Response, Error> {
+    let (target_name, fragment) = split_fragment(target);
+    let target_title = match PageTitle::parse_link(target_name, &app.config.titles) {
+        Ok(target_title) if target_title != *title => target_title,
+        _ => return view_redirect_display(app, title, target, RedirectQuery::default()),
+    };
+
+    let query = serde_html_form::to_string([("rdfrom", title.display())])
+        .expect("encoding a title as a query should not fail");
+    let fragment = fragment
+        .map(|f| format!("#{}", encode_url(f)))
+        .unwrap_or_default();
+    Ok(Redirect::to(&format!(
+        "/w/page/{}?{query}{fragment}",
+        target_title.query()
+    ))
+    .into_response())
+}
+
+fn view_redirect_display(
+    app: &App,
+    title: &PageTitle,
+    target: &str,
+    redirect: RedirectQuery,
+) -> Result<Response, Error> {
+    let (target, fragment) = split_fragment(target);
+    let config = &app.config.titles;
+    let Ok(target_title) = PageTitle::parse_link(target, config) else {
+        return render_page(
+            app,
+            "page/redirect",
+            json!({
+                "title": title.to_json(),
+                "redirect": {
+                    "from": redirected_from(config, &redirect),
+                    "target": {
+                        "display": target,
+                    },
+                    "status": "invalid",
+                },
+            }),
+        );
+    };
+
+    let status = if target_title == *title
+        || redirect
+            .rdfrom
+            .as_deref()
+            .and_then(|from| PageTitle::parse(from, config).ok())
+            == Some(target_title.clone())
+    {
+        "loop"
+    } else if redirect.rdfrom.is_some() {
+        "double"
+    } else {
+        "ok"
+    };
+
+    render_page(
+        app,
+        "page/redirect",
+        json!({
+            "title": title.to_json(),
+            "redirect": {
+                "from": redirected_from(config, &redirect),
+                "target": {
+                    "display": target_title.display(),
+                    "query": target_title.query(),
+                    "fragment": fragment.map(encode_url),
+                },
+                "status": status,
+            },
+        }),
+    )
+}
+
+fn redirected_from(config: &TitleConfig, redirect: &RedirectQuery) -> Option<serde_json::Value> {
+    redirect
+        .rdfrom
+        .as_deref()
+        .and_then(|from| PageTitle::parse(from, config).ok())
+        .map(|from| from.to_json())
+}
+
+fn view_page_editor<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    uri: OriginalUri,
+    page: Option<&Page>,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    if viewer.session.is_none() {
+        return Ok(redirect_to_login(&uri, ActionKind::Edit));
+    }
+    if !viewer.can(edit_permission(page)) {
+        return permission_denied(app, title, ActionKind::Edit);
+    }
+    if !may_edit(app, viewer) {
+        return email_unverified(app, title, ActionKind::Edit);
+    }
+    let content = page_content(page, conn)?.map(Body::into_text);
+
+    Ok(
+        if let Some(level) = check_protection(page, ProtectedAction::Edit, viewer, conn)? {
+            page_protected(app, title, ProtectedAction::Edit, level)?
+        } else {
+            render_page(
+                app,
+                "page/edit",
+                json!({
+                    "title": title.to_json(),
+                    "content": content.unwrap_or_default()
+                }),
+            )?
+        },
+    )
+}
+
+/// Splits a link target such as `Page#Section` into the title and the optional section fragment.
+fn split_fragment(target: &str) -> (&str, Option<&str>) {
+    match target.split_once('#') {
+        Some((title, fragment)) => (title, Some(fragment)),
+        None => (target, None),
+    }
+}
+
+/// Redirects to the login page, returning to the given action on the current page afterwards.
+fn redirect_to_login(uri: &OriginalUri, action: ActionKind) -> Response {
+    Redirect::to(&format!(
+        "/w/login?redirect_after={}&action={action}",
+        uri.path(),
+    ))
+    .into_response()
+}
+
+fn permission_denied(app: &App, title: &PageTitle, action: ActionKind) -> Result<Response, Error> {
+    Ok((
+        StatusCode::FORBIDDEN,
+        render_page(
+            app,
+            "page/permission-denied",
+            json!({
+                "title": title.to_json(),
+                "action": action.as_text(),
+            }),
+        )?,
+    )
+        .into_response())
+}
+
+/// Returns the permission needed to save `page`, which is creating it if it does not exist yet.
+fn edit_permission(page: Option<&Page>) -> Permission {
+    match page {
+        Some(_) => Permission::Edit,
+        None => Permission::Create,
+    }
+}
+
+/// Checks whether the viewer may edit at all, which may require them to have verified their
+/// e-mail address.
+pub(super) fn may_edit(app: &App, viewer: &Viewer) -> bool {
+    !app.config.require_verified_email || viewer.user.as_ref().is_some_and(User::is_email_verified)
+}
+
+pub(super) fn email_unverified(
+    app: &App,
+    title: &PageTitle,
+    action: ActionKind,
+) -> Result<Response, Error> {
+    Ok((
+        StatusCode::FORBIDDEN,
+        render_page(
+            app,
+            "page/unverified",
+            json!({
+                "title": title.to_json(),
+                "action": action.as_text(),
+            }),
+        )?,
+    )
+        .into_response())
+}
+
+/// Checks whether the viewer may perform `action` on `page`.
+///
+/// Returns the protection level of the action if they may not, and `None` otherwise.
+fn check_protection<C>(
+    page: Option<&Page>,
+    action: ProtectedAction,
+    viewer: &Viewer,
+    conn: &mut C,
+) -> Result<Option<ProtectionLevel>, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let Some(page) = page else {
+        return Ok(None);
+    };
+
+    let level = Protection::required_level(page.id, action, Utc::now(), conn)?;
+
+    Ok((!level.permits(&viewer.permissions)).then_some(level))
+}
+
+fn page_protected(
+    app: &App,
+    title: &PageTitle,
+    action: ProtectedAction,
+    level: ProtectionLevel,
+) -> Result<Response, Error> {
+    Ok((
+        StatusCode::FORBIDDEN,
+        render_page(
+            app,
+            "page/protected",
+            json!({
+                "title": title.to_json(),
+                "action": action.as_text(),
+                "level": level.name(),
+            }),
+        )?,
+    )
+        .into_response())
+}
+
+pub(crate) fn render_page(
+    app: &App,
+    template: &str,
+    page: serde_json::Value,
+) -> Result<Response, Error> {
+    Ok(Html::from(Response::builder().body(app.renderer.render(
+        template,
+        &Context::from_serialize(json!({
+            "site": {
+                "title": &app.config.title
+            },
+            "page": page
+        }))?,
+    )?)?)
+    .into_response())
+}
@@ -0,0 +1,122 @@
+use axum::{
+    extract::OriginalUri,
+    response::{IntoResponse, Redirect, Response},
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::{connection::LoadConnection, pg::Pg, Connection};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{
+    page_not_found, permission_denied, redirect_to_login, render_page, ActionKind, RedirectQuery,
+};
+use crate::{
+    auth::Permission,
+    controllers::wiki::viewer::Viewer,
+    model::{
+        page::Page,
+        protection::{ProtectedAction, Protection, ProtectionLevel},
+    },
+    title::PageTitle,
+    App, Error,
+};
+
+#[derive(Deserialize)]
+pub struct ProtectPage {
+    pub edit: ProtectionLevel,
+    #[serde(rename = "move")]
+    pub move_: ProtectionLevel,
+    pub expiry: ProtectionExpiry,
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtectionExpiry {
+    Infinite,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl ProtectionExpiry {
+    pub fn expire_on(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        Some(
+            now + match self {
+                Self::Infinite => return None,
+                Self::Hour => TimeDelta::hours(1),
+                Self::Day => TimeDelta::days(1),
+                Self::Week => TimeDelta::weeks(1),
+                Self::Month => TimeDelta::days(30),
+                Self::Year => TimeDelta::days(365),
+            },
+        )
+    }
+}
+
+pub(super) fn view_protect<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    uri: OriginalUri,
+    page: Option<Page>,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    if viewer.session.is_none() {
+        return Ok(redirect_to_login(&uri, ActionKind::Protect));
+    }
+    if !viewer.can(Permission::Protect) {
+        return permission_denied(app, title, ActionKind::Protect);
+    }
+    let Some(page) = page else {
+        return page_not_found(app, title, RedirectQuery::default(), conn);
+    };
+
+    let now = Utc::now();
+    render_page(
+        app,
+        "page/protect",
+        json!({
+            "title": title.to_json(),
+            "levels": {
+                "edit": Protection::required_level(page.id, ProtectedAction::Edit, now, conn)?.name(),
+                "move": Protection::required_level(page.id, ProtectedAction::Move, now, conn)?.name(),
+            },
+        }),
+    )
+}
+
+pub(super) fn submit_protect<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    protect: ProtectPage,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let Some(user) = viewer.user_with(Permission::Protect) else {
+        return permission_denied(app, title, ActionKind::Protect);
+    };
+    let Some(page) = Page::by_title(title.namespace, &title.key(), conn)? else {
+        return page_not_found(app, title, RedirectQuery::default(), conn);
+    };
+
+    let expire_on = protect.expiry.expire_on(Utc::now());
+    let reason = protect.reason.trim();
+    for (action, level) in [
+        (ProtectedAction::Edit, protect.edit),
+        (ProtectedAction::Move, protect.move_),
+    ] {
+        Protection::set(page.id, action, level, expire_on, reason, user.id, conn)?;
+    }
+
+    Ok(Redirect::to(&format!("/w/page/{}", title.query())).into_response())
+}
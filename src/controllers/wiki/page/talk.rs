@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use diesel::{connection::LoadConnection, pg::Pg, Connection};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{
+    check_protection, email_unverified, may_edit, page_content, page_protected, permission_denied,
+    redirected_from, render_page, ActionKind, RedirectQuery, DATE_FORMAT,
+};
+use crate::{
+    auth::Permission,
+    controllers::wiki::viewer::Viewer,
+    model::{
+        discussion::{NewPost, NewThread, Post, Thread},
+        page::Page,
+        protection::ProtectedAction,
+    },
+    output::Body,
+    title::PageTitle,
+    App, Error,
+};
+
+/// The maximum length of a thread subject in characters, as stored in `discussion_threads.subject`.
+const MAX_SUBJECT_LENGTH: usize = 255;
+
+#[derive(Deserialize)]
+pub struct StartThread {
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReplyToPost {
+    pub thread: i64,
+    /// The post being replied to; replies without one are added to the end of the thread.
+    #[serde(default)]
+    pub parent: Option<i64>,
+    pub body: String,
+}
+
+/// Posts along with their author's name, keyed by their thread and the post they reply to.
+type Replies = HashMap<(i64, Option<i64>), Vec<(Post, String)>>;
+
+/// Shows a talk page: its free-text content, if any, followed by its discussion threads.
+pub(super) fn view_talk<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    page: Option<&Page>,
+    content: Option<String>,
+    redirect: RedirectQuery,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let mut talk = talk_json(app, viewer, title, page, content, conn)?;
+    talk["redirect"] = json!({
+        "from": redirected_from(&app.config.titles, &redirect),
+    });
+
+    render_page(app, "page/talk", talk)
+}
+
+/// Returns the content and threads of a talk page, as passed into the template.
+fn talk_json<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    page: Option<&Page>,
+    content: Option<String>,
+    conn: &mut C,
+) -> Result<serde_json::Value, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let protected = check_protection(page, ProtectedAction::Edit, viewer, conn)?;
+    let can_post = viewer.user_with(Permission::Edit).is_some()
+        && may_edit(app, viewer)
+        && protected.is_none();
+
+    let threads = Thread::by_title(title.namespace, &title.key(), conn)?;
+    let thread_ids = threads
+        .iter()
+        .map(|(thread, _)| thread.id)
+        .collect::<Vec<_>>();
+
+    let mut replies = Replies::new();
+    for (post, user_name) in Post::by_thread_ids(&thread_ids, conn)? {
+        replies
+            .entry((post.thread_id, post.parent_id))
+            .or_default()
+            .push((post, user_name));
+    }
+
+    let threads = threads
+        .into_iter()
+        .map(|(thread, user_name)| {
+            let mut posts = Vec::new();
+            flatten_posts(&mut replies, thread.id, None, 0, &mut posts);
+
+            json!({
+                "id": thread.id,
+                "subject": thread.subject,
+                "user": user_name,
+                "created_on": thread.created_on.format(DATE_FORMAT).to_string(),
+                "posts": posts,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "title": title.to_json(),
+        "content": content,
+        "redirect": {
+            "from": null,
+        },
+        "threads": threads,
+        "can_post": can_post,
+        "protected": protected.map(|level| level.name()),
+        "unverified": viewer.user.is_some() && !may_edit(app, viewer),
+    }))
+}
+
+/// Appends the replies to `parent` in `thread_id` to `out` depth-first, so that every post is
+/// followed by the replies to it.
+fn flatten_posts(
+    replies: &mut Replies,
+    thread_id: i64,
+    parent: Option<i64>,
+    depth: usize,
+    out: &mut Vec<serde_json::Value>,
+) {
+    for (post, user_name) in replies.remove(&(thread_id, parent)).unwrap_or_default() {
+        out.push(json!({
+            "id": post.id,
+            "body": post.body,
+            "user": user_name,
+            "created_on": post.created_on.format(DATE_FORMAT).to_string(),
+            "depth": depth,
+        }));
+        flatten_posts(replies, thread_id, Some(post.id), depth + 1, out);
+    }
+}
+
+pub(super) fn submit_new_thread<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    start: StartThread,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let Some(user) = viewer.user_with(Permission::Edit) else {
+        return permission_denied(app, title, ActionKind::NewThread);
+    };
+    if !may_edit(app, viewer) {
+        return email_unverified(app, title, ActionKind::Edit);
+    }
+    // posting is editing the talk page, so it is protected along with its content
+    let page = Page::by_title(title.namespace, &title.key(), conn)?;
+    if let Some(level) = check_protection(page.as_ref(), ProtectedAction::Edit, viewer, conn)? {
+        return page_protected(app, title, ProtectedAction::Edit, level);
+    }
+    let (subject, body) = (start.subject.trim(), start.body.trim());
+    if subject.is_empty() || body.is_empty() {
+        return Ok(Redirect::to(&format!("/w/page/{}", title.query())).into_response());
+    }
+    let length = subject.chars().count();
+    if length > MAX_SUBJECT_LENGTH {
+        // the thread is not started, so the form is shown again with what was entered
+        let content = page_content(page.as_ref(), conn)?.map(Body::into_text);
+        let mut talk = talk_json(app, viewer, title, page.as_ref(), content, conn)?;
+        talk["draft"] = json!({
+            "subject": start.subject,
+            "body": start.body,
+            "error": TalkError::SubjectTooLong(length).to_string(),
+        });
+
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            render_page(app, "page/talk", talk)?,
+        )
+            .into_response());
+    }
+
+    let thread = conn.transaction(|conn| {
+        let thread =
+            NewThread::new(title.namespace, &title.key(), subject, user.id).insert(conn)?;
+        NewPost::new(thread.id, None, body, user.id).insert(conn)?;
+
+        Ok::<_, Error>(thread)
+    })?;
+
+    Ok(Redirect::to(&format!("/w/page/{}#thread-{}", title.query(), thread.id)).into_response())
+}
+
+pub(super) fn submit_reply<C>(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    reply: ReplyToPost,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let Some(user) = viewer.user_with(Permission::Edit) else {
+        return permission_denied(app, title, ActionKind::Reply);
+    };
+    if !may_edit(app, viewer) {
+        return email_unverified(app, title, ActionKind::Edit);
+    }
+    let page = Page::by_title(title.namespace, &title.key(), conn)?;
+    if let Some(level) = check_protection(page.as_ref(), ProtectedAction::Edit, viewer, conn)? {
+        return page_protected(app, title, ProtectedAction::Edit, level);
+    }
+    let back = Redirect::to(&format!("/w/page/{}", title.query())).into_response();
+
+    let body = reply.body.trim();
+    let Some(thread) = Thread::by_id(reply.thread, conn)? else {
+        return Ok(back);
+    };
+    if body.is_empty() || thread.namespace != title.namespace || thread.title != title.key() {
+        return Ok(back);
+    }
+    if let Some(parent) = reply.parent {
+        if Post::by_id(parent, conn)?.is_none_or(|parent| parent.thread_id != thread.id) {
+            return Ok(back);
+        }
+    }
+
+    let post = NewPost::new(thread.id, reply.parent, body, user.id).insert(conn)?;
+
+    Ok(Redirect::to(&format!("/w/page/{}#post-{}", title.query(), post.id)).into_response())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TalkError {
+    #[error("the subject is {0} characters long, but at most {MAX_SUBJECT_LENGTH} are allowed")]
+    SubjectTooLong(usize),
+}
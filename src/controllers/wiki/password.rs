@@ -0,0 +1,100 @@
+use axum::{
+    debug_handler,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use tera::Context;
+
+use crate::{
+    auth::{Password, PolicyError},
+    controllers::wiki::{csrf::Csrf, viewer::LoggedIn},
+    App, AppState, Error,
+};
+
+#[debug_handler(state = AppState)]
+pub async fn get(AppState(app): AppState, _: LoggedIn) -> Result<Response, Error> {
+    render_password(&app, None, false)
+}
+
+#[debug_handler(state = AppState)]
+pub async fn post(
+    AppState(app): AppState,
+    LoggedIn { session, user, .. }: LoggedIn,
+    Csrf(Form(data)): Csrf<Form<PasswordChangeData>>,
+) -> Result<Response, Error> {
+    let conn = &mut app.db.pool.get()?;
+
+    let policy = &app.config.passwords;
+    let result = if !user
+        .password
+        .compare(&data.current, policy)
+        .unwrap_or(false)
+    {
+        Err(PasswordChangeError::WrongPassword)
+    } else if data.password != data.confirm {
+        Err(PasswordChangeError::Mismatch)
+    } else {
+        policy.check(&data.password).map_err(Into::into)
+    };
+    if let Err(error) = result {
+        return render_password(&app, Some(error), false);
+    }
+
+    let password = Password::generate_current(&data.password, None, policy)?;
+    _ = user.update_password(&password, conn)?;
+    _ = user.mark_updated(Utc::now(), conn)?;
+    // whoever knew the old password should not stay logged in elsewhere
+    _ = session.delete_others(conn)?;
+
+    render_password(&app, None, true)
+}
+
+fn render_password(
+    app: &App,
+    error: Option<PasswordChangeError>,
+    changed: bool,
+) -> Result<Response, Error> {
+    let status = error
+        .as_ref()
+        .map_or(StatusCode::OK, PasswordChangeError::status);
+    let html = app.renderer.render(
+        "password",
+        &Context::from_serialize(json!({
+            "error": error.as_ref().map(ToString::to_string),
+            "changed": changed,
+        }))?,
+    )?;
+
+    Ok((status, Html::from(html)).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct PasswordChangeData {
+    pub current: String,
+    pub password: String,
+    /// The new password entered a second time, to catch typos.
+    pub confirm: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasswordChangeError {
+    #[error("the current password is wrong")]
+    WrongPassword,
+    #[error("the new passwords do not match")]
+    Mismatch,
+    #[error(transparent)]
+    WeakPassword(#[from] PolicyError),
+}
+
+impl PasswordChangeError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::WrongPassword => StatusCode::FORBIDDEN,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
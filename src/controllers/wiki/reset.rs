@@ -0,0 +1,210 @@
+use axum::{
+    debug_handler,
+    extract::Query,
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    Form,
+};
+use chrono::Utc;
+use diesel::Connection;
+use serde::Deserialize;
+use serde_json::json;
+use tera::Context;
+
+use crate::{
+    auth::{Password, PolicyError},
+    controllers::wiki::{client::ClientAddress, csrf::Csrf},
+    model::{
+        login::ResetRequest,
+        user::{PasswordReset, Session, User},
+    },
+    App, AppState, Error,
+};
+
+#[debug_handler(state = AppState)]
+pub async fn get(
+    AppState(app): AppState,
+    Query(query): Query<ResetQuery>,
+) -> Result<Response, Error> {
+    let Some(token) = query.token else {
+        return render_reset(&app, ResetStage::Request, None, None);
+    };
+
+    let conn = &mut app.db.pool.get()?;
+    if PasswordReset::from_token(&token, &app.config.sessions, Utc::now(), conn)?.is_none() {
+        return render_reset(&app, ResetStage::Invalid, None, None);
+    }
+
+    render_reset(&app, ResetStage::Choose, None, Some(&token))
+}
+
+#[debug_handler(state = AppState)]
+pub async fn post(
+    AppState(app): AppState,
+    ClientAddress(address): ClientAddress,
+    Csrf(Form(data)): Csrf<Form<ResetData>>,
+) -> Result<Response, Error> {
+    let conn = &mut app.db.pool.get()?;
+    let now = Utc::now();
+
+    let Some(token) = data.token else {
+        // the same page is shown whether or not the account exists, to not reveal who has one
+        let login = data.login.unwrap_or_default();
+        let user = if login.contains('@') {
+            User::by_email(login.trim(), conn)?
+        } else {
+            User::by_name(login.trim(), conn)?
+        };
+        let user_id = user.as_ref().map(|user| user.id);
+        if ResetRequest::locked_until(user_id, &address, now, conn)?.is_some() {
+            // refused requests are not recorded, so that asking on does not extend the lockout
+            return render_reset(&app, ResetStage::Sent, None, None);
+        }
+        _ = ResetRequest::record(user_id, &address, now, conn)?;
+
+        if let Some(user) = user {
+            let token = PasswordReset::generate(user.id, &app.config.sessions, now, conn)?;
+            let subject = format!("Reset your password on {}", app.config.title);
+            let body = format!(
+                "Someone, hopefully you, asked to reset the password of {} on {}.\n\n\
+                 To choose a new password, open this link within {} minutes:\n\n\
+                 {}/w/reset?token={}\n\n\
+                 If you did not ask for this, you can ignore this message.",
+                user.name,
+                app.config.title,
+                PasswordReset::LIFETIME.num_minutes(),
+                app.config.public_url,
+                token,
+            );
+            // sent in the background, so that neither the time taken nor a failure to send tells
+            // that the account exists
+            let app = app.clone();
+            _ = tokio::task::spawn_blocking(move || {
+                if let Err(error) = app.config.mail.send(&user.email, &subject, &body) {
+                    println!(
+                        "> cannot send password reset e-mail to user {}: {error}",
+                        user.id
+                    );
+                }
+            });
+        }
+
+        return render_reset(&app, ResetStage::Sent, None, None);
+    };
+
+    let config = &app.config.sessions;
+    let Some(reset) = PasswordReset::from_token(&token, config, now, conn)? else {
+        return render_reset(&app, ResetStage::Invalid, None, None);
+    };
+
+    let password = data.password.unwrap_or_default();
+    let result = if Some(&password) != data.confirm.as_ref() {
+        Err(ResetError::Mismatch)
+    } else {
+        app.config
+            .passwords
+            .check(&password)
+            .map_err(ResetError::from)
+    };
+    if let Err(error) = result {
+        return render_reset(&app, ResetStage::Choose, Some(error), Some(&token));
+    }
+
+    let password = Password::generate_current(&password, None, &app.config.passwords)?;
+    let reset = conn.transaction::<_, Error, _>(|conn| {
+        // redeeming first makes sure that the token is only ever used once
+        if !reset.redeem(conn)? {
+            return Ok(false);
+        }
+        let Some(user) = User::by_id(reset.user_id, conn)? else {
+            return Ok(false);
+        };
+
+        _ = user.update_password(&password, conn)?;
+        _ = user.mark_updated(now, conn)?;
+        _ = Session::delete_all_by_user_id(user.id, conn)?;
+
+        Ok(true)
+    })?;
+    if !reset {
+        return render_reset(&app, ResetStage::Invalid, None, None);
+    }
+
+    Ok(Redirect::to("/w/login?status=reset").into_response())
+}
+
+fn render_reset(
+    app: &App,
+    stage: ResetStage,
+    error: Option<ResetError>,
+    token: Option<&str>,
+) -> Result<Response, Error> {
+    let status = match (&stage, &error) {
+        (_, Some(_)) => StatusCode::BAD_REQUEST,
+        (ResetStage::Invalid, None) => StatusCode::NOT_FOUND,
+        _ => StatusCode::OK,
+    };
+    let html = app.renderer.render(
+        "reset",
+        &Context::from_serialize(json!({
+            "stage": stage.name(),
+            "error": error.as_ref().map(ToString::to_string),
+            "token": token,
+        }))?,
+    )?;
+
+    Ok((status, Html::from(html)).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct ResetQuery {
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Either a request for a reset e-mail with `login`, or a new password for the reset of `token`.
+#[derive(Deserialize)]
+pub struct ResetData {
+    /// The username or e-mail address of the account.
+    #[serde(default)]
+    pub login: Option<String>,
+
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// The new password entered a second time, to catch typos.
+    #[serde(default)]
+    pub confirm: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResetStage {
+    /// Asking for the account to send the e-mail for.
+    Request,
+    /// Telling that the e-mail was sent.
+    Sent,
+    /// Asking for the new password.
+    Choose,
+    /// Telling that the link has expired or was already used.
+    Invalid,
+}
+
+impl ResetStage {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Request => "request",
+            Self::Sent => "sent",
+            Self::Choose => "choose",
+            Self::Invalid => "invalid",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResetError {
+    #[error("the passwords do not match")]
+    Mismatch,
+    #[error(transparent)]
+    WeakPassword(#[from] PolicyError),
+}
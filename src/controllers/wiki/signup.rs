@@ -0,0 +1,216 @@
+use axum::{
+    debug_handler,
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    Form,
+};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use diesel::Connection;
+use serde::Deserialize;
+use serde_json::json;
+use tera::Context;
+
+use crate::{
+    auth::{Password, PasswordPolicy, PolicyError},
+    controllers::wiki::{
+        csrf::Csrf, email::send_verification, login::session_cookie, viewer::Viewer,
+    },
+    model::user::{NewUser, Session},
+    title::FORBIDDEN_CHARS,
+    App, AppState, Error,
+};
+
+/// The maximum length of a username in characters, as stored in `users.name`.
+const MAX_NAME_LENGTH: usize = 255;
+/// The maximum length of an e-mail address in characters, as stored in `users.email`.
+const MAX_EMAIL_LENGTH: usize = 320;
+
+#[debug_handler(state = AppState)]
+pub async fn get(AppState(app): AppState) -> Result<Response, Error> {
+    if !app.config.allow_registration {
+        return render_signup(&app, Some(SignupError::Disabled), None);
+    }
+
+    render_signup(&app, None, None)
+}
+
+#[debug_handler(state = AppState)]
+pub async fn post(
+    AppState(app): AppState,
+    viewer: Viewer,
+    mut jar: CookieJar,
+    Csrf(Form(data)): Csrf<Form<SignupData>>,
+) -> Result<(CookieJar, Response), Error> {
+    if !app.config.allow_registration {
+        return Ok((jar, render_signup(&app, Some(SignupError::Disabled), None)?));
+    }
+    if viewer.session.is_some() {
+        return Ok((
+            jar,
+            render_signup(&app, Some(SignupError::LoggedIn), Some(&data))?,
+        ));
+    }
+    if let Err(error) = data.validate(&app.config.passwords) {
+        return Ok((jar, render_signup(&app, Some(error), Some(&data))?));
+    }
+
+    let conn = &mut app.db.pool.get()?;
+    let password = Password::generate_current(&data.password, None, &app.config.passwords)?;
+    let user = conn.transaction::<_, Error, _>(|conn| {
+        let new_user = NewUser::new(data.name.trim(), data.email.trim(), password, None);
+        if new_user.has_conflict(conn)? {
+            return Ok(None);
+        }
+
+        Ok(Some(new_user.insert(conn)?))
+    })?;
+    let Some(user) = user else {
+        return Ok((
+            jar,
+            render_signup(&app, Some(SignupError::Conflict), Some(&data))?,
+        ));
+    };
+
+    send_verification(&app, &user, &user.email, conn)?;
+
+    let now = Utc::now();
+    let (session, token) = Session::generate(user.id, false, &app.config.sessions, now, conn)?;
+    jar = jar.add(session_cookie(&app.config.sessions, &session, &token, now));
+    _ = session.insert(conn)?;
+
+    Ok((jar, Redirect::to("/w/").into_response()))
+}
+
+/// Renders the sign-up form, keeping the entered name and e-mail address after an error.
+fn render_signup(
+    app: &App,
+    error: Option<SignupError>,
+    data: Option<&SignupData>,
+) -> Result<Response, Error> {
+    let status = error.as_ref().map_or(StatusCode::OK, SignupError::status);
+    let html = app.renderer.render(
+        "signup",
+        &Context::from_serialize(json!({
+            "error": error.as_ref().map(ToString::to_string),
+            "disabled": matches!(error, Some(SignupError::Disabled)),
+            "name": data.map(|data| data.name.trim()),
+            "email": data.map(|data| data.email.trim()),
+        }))?,
+    )?;
+
+    Ok((status, Html::from(html)).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct SignupData {
+    pub name: String,
+    pub email: String,
+    pub password: String,
+    /// The password entered a second time, to catch typos.
+    pub confirm: String,
+}
+
+impl SignupData {
+    fn validate(&self, policy: &PasswordPolicy) -> Result<(), SignupError> {
+        validate_name(self.name.trim())?;
+        if !is_valid_email(self.email.trim()) {
+            return Err(SignupError::InvalidEmail);
+        }
+        policy.check(&self.password)?;
+        if self.password != self.confirm {
+            return Err(SignupError::PasswordMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that a username can be told apart from an e-mail address when logging in, and can be
+/// used in page titles.
+fn validate_name(name: &str) -> Result<(), SignupError> {
+    if name.is_empty() {
+        return Err(SignupError::EmptyName);
+    }
+    let len = name.chars().count();
+    if len > MAX_NAME_LENGTH {
+        return Err(SignupError::NameTooLong(len));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| c.is_control() || FORBIDDEN_CHARS.contains(c) || matches!(c, '@' | '/'))
+    {
+        return Err(SignupError::ForbiddenChar(c));
+    }
+
+    Ok(())
+}
+
+/// Checks that `email` has the shape `local@domain.tld`, without attempting full RFC 5322
+/// validation.
+pub(crate) fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+
+    email.chars().count() <= MAX_EMAIL_LENGTH
+        && !email.chars().any(|c| c.is_whitespace() || c.is_control())
+        && !local.is_empty()
+        && !domain.contains('@')
+        && domain.contains('.')
+        && domain.split('.').all(|label| !label.is_empty())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignupError {
+    #[error("registration is disabled on this wiki, ask an administrator for an account")]
+    Disabled,
+    #[error("you are already logged in")]
+    LoggedIn,
+    #[error("the username is empty")]
+    EmptyName,
+    #[error("the username is {0} characters long, but at most {MAX_NAME_LENGTH} are allowed")]
+    NameTooLong(usize),
+    #[error("the username contains the character {0:?}, which is not allowed in usernames")]
+    ForbiddenChar(char),
+    #[error("the e-mail address is not valid")]
+    InvalidEmail,
+    #[error(transparent)]
+    WeakPassword(#[from] PolicyError),
+    #[error("the passwords do not match")]
+    PasswordMismatch,
+    #[error("the username or e-mail address is already in use")]
+    Conflict,
+}
+
+impl SignupError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Disabled => StatusCode::FORBIDDEN,
+            Self::Conflict => StatusCode::CONFLICT,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[test]
+fn signup_validation_test() {
+    assert!(validate_name("Euclid of Alexandria").is_ok());
+    assert!(matches!(validate_name(""), Err(SignupError::EmptyName)));
+    assert!(matches!(
+        validate_name("euclid@example.org"),
+        Err(SignupError::ForbiddenChar('@'))
+    ));
+    assert!(matches!(
+        validate_name("Euclid/Elements"),
+        Err(SignupError::ForbiddenChar('/'))
+    ));
+
+    assert!(is_valid_email("euclid@alexandria.example"));
+    assert!(!is_valid_email("euclid"));
+    assert!(!is_valid_email("@alexandria.example"));
+    assert!(!is_valid_email("euclid@localhost"));
+    assert!(!is_valid_email("euclid@alexandria..example"));
+    assert!(!is_valid_email("euclid @alexandria.example"));
+    assert!(!is_valid_email("euclid@a@alexandria.example"));
+}
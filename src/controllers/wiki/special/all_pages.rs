@@ -0,0 +1,59 @@
+use axum::response::Response;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{Paging, SpecialRequest};
+use crate::{
+    controllers::wiki::page::render_page,
+    model::{namespace::Namespace, page::Page},
+    title::PageTitle,
+    App, Error,
+};
+
+#[derive(Deserialize)]
+struct AllPagesQuery {
+    /// The id of the namespace to list; defaults to the main namespace.
+    #[serde(default)]
+    namespace: Option<i16>,
+}
+
+/// Lists the pages of a namespace in alphabetical order.
+pub(super) fn view(app: &App, request: &SpecialRequest) -> Result<Response, Error> {
+    let query = request.query::<AllPagesQuery>()?;
+    let paging = request.query::<Paging>()?;
+    let namespace = query
+        .namespace
+        .and_then(Namespace::from_id)
+        .filter(|namespace| !namespace.is_special())
+        .unwrap_or_default();
+
+    let conn = &mut app.db.pool.get()?;
+    let mut titles = Page::titles(namespace, paging.offset(), paging.limit() + 1, conn)?;
+    let paging = paging.split(request, &mut titles);
+
+    let namespaces = Namespace::ALL
+        .iter()
+        .filter(|namespace| !namespace.is_special())
+        .map(|ns| {
+            json!({
+                "id": ns.id(),
+                "name": ns.name(),
+                "selected": *ns == namespace,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    render_page(
+        app,
+        "special/all-pages",
+        json!({
+            "title": request.title.to_json(),
+            "namespaces": namespaces,
+            "pages": titles
+                .iter()
+                .map(|title| PageTitle::from_stored(namespace, title).to_json())
+                .collect::<Vec<_>>(),
+            "paging": paging,
+        }),
+    )
+}
@@ -0,0 +1,34 @@
+use axum::response::Response;
+use serde_json::json;
+
+use super::{Paging, SpecialRequest};
+use crate::{
+    controllers::wiki::page::render_page, model::page::Page, title::PageTitle, App, Error,
+};
+
+/// Lists pages other than redirects by the size of their content, largest first.
+pub(super) fn view(app: &App, request: &SpecialRequest) -> Result<Response, Error> {
+    let paging = request.query::<Paging>()?;
+
+    let conn = &mut app.db.pool.get()?;
+    let mut pages = Page::longest(paging.offset(), paging.limit() + 1, conn)?;
+    let paging = paging.split(request, &mut pages);
+
+    render_page(
+        app,
+        "special/long-pages",
+        json!({
+            "title": request.title.to_json(),
+            "pages": pages
+                .iter()
+                .map(|(namespace, title, size)| {
+                    json!({
+                        "title": PageTitle::from_stored(*namespace, title).to_json(),
+                        "size": size,
+                    })
+                })
+                .collect::<Vec<_>>(),
+            "paging": paging,
+        }),
+    )
+}
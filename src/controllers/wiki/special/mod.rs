@@ -0,0 +1,319 @@
+mod all_pages;
+mod long_pages;
+mod new_pages;
+mod random_page;
+mod redirects;
+mod reports;
+mod search;
+
+use axum::{
+    extract::OriginalUri,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+
+use crate::{
+    controllers::wiki::{page::render_page, viewer::Viewer},
+    model::{namespace::Namespace, protection::ProtectionLevel},
+    title::{encode_url, PageTitle},
+    App, Error,
+};
+
+/// A page generated by the software itself rather than stored, such as `Special:AllPages`.
+pub struct SpecialPage {
+    /// The canonical name of the page, following the `Special:` prefix.
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The level a user must have to view the page.
+    pub permission: ProtectionLevel,
+    /// Whether responses may be cached by browsers and proxies for a short while.
+    pub cacheable: bool,
+    pub handler: fn(&App, &SpecialRequest) -> Result<Response, Error>,
+}
+
+/// All special pages, in the order they are listed on `Special:SpecialPages`.
+pub const SPECIAL_PAGES: &[SpecialPage] = &[
+    SpecialPage {
+        name: "SpecialPages",
+        description: "List of all special pages",
+        permission: ProtectionLevel::None,
+        cacheable: true,
+        handler: view_special_pages,
+    },
+    SpecialPage {
+        name: "Search",
+        description: "Search the text of all pages",
+        permission: ProtectionLevel::None,
+        cacheable: false,
+        handler: search::view,
+    },
+    SpecialPage {
+        name: "AllPages",
+        description: "All pages in a namespace, in alphabetical order",
+        permission: ProtectionLevel::None,
+        cacheable: true,
+        handler: all_pages::view,
+    },
+    SpecialPage {
+        name: "RandomPage",
+        description: "Go to a random article",
+        permission: ProtectionLevel::None,
+        cacheable: false,
+        handler: random_page::view,
+    },
+    SpecialPage {
+        name: "NewPages",
+        description: "The most recently created pages",
+        permission: ProtectionLevel::None,
+        cacheable: false,
+        handler: new_pages::view,
+    },
+    SpecialPage {
+        name: "LongPages",
+        description: "Pages by the size of their content, largest first",
+        permission: ProtectionLevel::None,
+        cacheable: true,
+        handler: long_pages::view,
+    },
+    SpecialPage {
+        name: "OrphanedPages",
+        description: "Articles which no other page links to",
+        permission: ProtectionLevel::None,
+        cacheable: true,
+        handler: reports::view_orphaned,
+    },
+    SpecialPage {
+        name: "DeadendPages",
+        description: "Articles which do not link to any other page",
+        permission: ProtectionLevel::None,
+        cacheable: true,
+        handler: reports::view_dead_end,
+    },
+    SpecialPage {
+        name: "WantedPages",
+        description: "Missing pages with the most links to them",
+        permission: ProtectionLevel::None,
+        cacheable: true,
+        handler: reports::view_wanted,
+    },
+    SpecialPage {
+        name: "Redirects",
+        description: "All redirects, along with broken and double redirects",
+        permission: ProtectionLevel::None,
+        cacheable: true,
+        handler: redirects::view,
+    },
+];
+
+/// How long cacheable special pages may be cached for, in seconds.
+const CACHE_MAX_AGE: u32 = 300;
+
+impl SpecialPage {
+    /// Looks up a special page by name, case-insensitively.
+    pub fn by_name(name: &str) -> Option<&'static Self> {
+        SPECIAL_PAGES
+            .iter()
+            .find(|special| special.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn title(&self) -> PageTitle {
+        PageTitle::new(Namespace::Special, self.name.to_string())
+    }
+}
+
+/// A request for a special page, passed to its handler.
+pub struct SpecialRequest<'a> {
+    pub title: &'a PageTitle,
+    /// The part of the title after the first slash, as in `Special:AllPages/Help`.
+    pub subpage: Option<&'a str>,
+    /// The raw query string of the request.
+    pub query: &'a str,
+}
+
+impl SpecialRequest<'_> {
+    /// Decodes the query string into the parameters expected by the handler.
+    pub fn query<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(serde_html_form::from_str(self.query)?)
+    }
+
+    /// Returns the query string with `key` set to `value`, for linking to a variation of the page.
+    pub fn query_with(&self, key: &str, value: &str) -> String {
+        let mut pairs = serde_html_form::from_str::<Vec<(String, String)>>(self.query)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(k, _)| k != key)
+            .collect::<Vec<_>>();
+        pairs.push((key.to_string(), value.to_string()));
+
+        serde_html_form::to_string(pairs).expect("encoding query pairs should not fail")
+    }
+}
+
+/// Query parameters selecting one page of a long listing.
+#[derive(Debug, Deserialize)]
+pub struct Paging {
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default = "Paging::default_limit")]
+    pub limit: i64,
+}
+
+impl Paging {
+    const MAX_LIMIT: i64 = 500;
+
+    fn default_limit() -> i64 {
+        50
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset.max(0)
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.clamp(1, Self::MAX_LIMIT)
+    }
+
+    /// Trims `rows`, fetched with a limit of one more than `self.limit()`, down to the listed page.
+    ///
+    /// Returns the query strings for the previous and next pages of the listing, if any, as
+    /// passed into templates.
+    pub fn split<T>(&self, request: &SpecialRequest, rows: &mut Vec<T>) -> serde_json::Value {
+        let (offset, limit) = (self.offset(), self.limit());
+        let has_next = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+
+        json!({
+            "offset": offset,
+            "prev": (offset > 0)
+                .then(|| request.query_with("offset", &(offset - limit).max(0).to_string())),
+            "next": has_next.then(|| request.query_with("offset", &(offset + limit).to_string())),
+        })
+    }
+}
+
+/// Responds with the special page named by `title`, which must be in the special namespace.
+pub(crate) fn view_special(
+    app: &App,
+    viewer: &Viewer,
+    title: &PageTitle,
+    uri: &OriginalUri,
+) -> Result<Response, Error> {
+    let (name, subpage) = match title.name.split_once('/') {
+        Some((name, subpage)) => (name, Some(subpage)),
+        None => (title.name.as_str(), None),
+    };
+    let Some(special) = SpecialPage::by_name(name) else {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            render_page(
+                app,
+                "special/not-found",
+                json!({
+                    "title": title.to_json(),
+                }),
+            )?,
+        )
+            .into_response());
+    };
+
+    if special.name != name {
+        let mut target = special.title().query();
+        if let Some(subpage) = subpage {
+            target = format!("{target}/{}", encode_url(subpage));
+        }
+        if let Some(query) = uri.query() {
+            target = format!("{target}?{query}");
+        }
+        return Ok(Redirect::permanent(&format!("/w/page/{target}")).into_response());
+    }
+
+    if !special.permission.permits(&viewer.permissions) {
+        return Ok((
+            StatusCode::FORBIDDEN,
+            render_page(
+                app,
+                "page/permission-denied",
+                json!({
+                    "title": title.to_json(),
+                    "action": "view",
+                }),
+            )?,
+        )
+            .into_response());
+    }
+
+    let mut response = (special.handler)(
+        app,
+        &SpecialRequest {
+            title,
+            subpage,
+            query: uri.query().unwrap_or_default(),
+        },
+    )?;
+    let cache_control = if special.cacheable {
+        // pages seen by a logged in user may differ from what visitors see, and come with their
+        // renewed session cookie
+        let scope = if viewer.session.is_some() {
+            "private"
+        } else {
+            "public"
+        };
+        HeaderValue::from_str(&format!("{scope}, max-age={CACHE_MAX_AGE}"))
+            .expect("cache control header should be valid")
+    } else {
+        HeaderValue::from_static("no-store")
+    };
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, cache_control);
+
+    Ok(response)
+}
+
+/// Middleware keeping responses which set cookies out of shared caches.
+///
+/// The session and CSRF cookies are only added after the handlers ran, so cacheable special
+/// pages cannot tell whether their response will carry one.
+pub async fn keep_cookies_private(mut response: Response) -> Response {
+    let public = response
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("public"))
+        .map(str::to_string);
+    if let Some(rest) = public {
+        if response.headers().contains_key(header::SET_COOKIE) {
+            let private = HeaderValue::from_str(&format!("private{rest}"))
+                .expect("cache control header should be valid");
+            _ = response
+                .headers_mut()
+                .insert(header::CACHE_CONTROL, private);
+        }
+    }
+
+    response
+}
+
+fn view_special_pages(app: &App, request: &SpecialRequest) -> Result<Response, Error> {
+    let pages = SPECIAL_PAGES
+        .iter()
+        .map(|special| {
+            json!({
+                "title": special.title().to_json(),
+                "description": special.description,
+                "permission": special.permission.name(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    render_page(
+        app,
+        "special/special-pages",
+        json!({
+            "title": request.title.to_json(),
+            "pages": pages,
+        }),
+    )
+}
@@ -0,0 +1,38 @@
+use axum::response::Response;
+use serde_json::json;
+
+use super::{Paging, SpecialRequest};
+use crate::{
+    controllers::wiki::page::{render_page, DATE_FORMAT},
+    model::page::Page,
+    title::PageTitle,
+    App, Error,
+};
+
+/// Lists the most recently created pages, newest first.
+pub(super) fn view(app: &App, request: &SpecialRequest) -> Result<Response, Error> {
+    let paging = request.query::<Paging>()?;
+
+    let conn = &mut app.db.pool.get()?;
+    let mut pages = Page::newest(paging.offset(), paging.limit() + 1, conn)?;
+    let paging = paging.split(request, &mut pages);
+
+    render_page(
+        app,
+        "special/new-pages",
+        json!({
+            "title": request.title.to_json(),
+            "pages": pages
+                .iter()
+                .map(|(page, user_name)| {
+                    json!({
+                        "title": PageTitle::from_stored(page.namespace, &page.title).to_json(),
+                        "user": user_name,
+                        "created_on": page.created_on.format(DATE_FORMAT).to_string(),
+                    })
+                })
+                .collect::<Vec<_>>(),
+            "paging": paging,
+        }),
+    )
+}
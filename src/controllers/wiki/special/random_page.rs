@@ -0,0 +1,29 @@
+use axum::response::{IntoResponse, Redirect, Response};
+
+use super::SpecialRequest;
+use crate::{
+    controllers::wiki::page::render_page,
+    model::{namespace::Namespace, page::Page},
+    title::PageTitle,
+    App, Error,
+};
+
+/// Redirects to an article picked at random.
+pub(super) fn view(app: &App, request: &SpecialRequest) -> Result<Response, Error> {
+    let conn = &mut app.db.pool.get()?;
+
+    Ok(match Page::random(Namespace::Main, conn)? {
+        Some(page) => Redirect::to(&format!(
+            "/w/page/{}",
+            PageTitle::from_stored(page.namespace, &page.title).query()
+        ))
+        .into_response(),
+        None => render_page(
+            app,
+            "special/random-page",
+            serde_json::json!({
+                "title": request.title.to_json(),
+            }),
+        )?,
+    })
+}
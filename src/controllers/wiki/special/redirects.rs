@@ -0,0 +1,111 @@
+use std::collections::{HashMap, HashSet};
+
+use axum::response::Response;
+use serde_json::json;
+
+use super::SpecialRequest;
+use crate::{
+    controllers::wiki::page::render_page,
+    model::page::{Page, PageRedirect},
+    title::PageTitle,
+    App, Error,
+};
+
+/// Lists all redirects along with whether they are broken, double or part of a loop.
+pub(super) fn view(app: &App, request: &SpecialRequest) -> Result<Response, Error> {
+    let conn = &mut app.db.pool.get()?;
+
+    let redirects = PageRedirect::list(conn)?
+        .into_iter()
+        .map(|((namespace, title), (target_namespace, target))| {
+            (
+                PageTitle::from_stored(namespace, &title),
+                PageTitle::from_stored(target_namespace, &target),
+            )
+        })
+        .collect::<Vec<_>>();
+    let sources = redirects
+        .iter()
+        .map(|(source, target)| (source, target))
+        .collect::<HashMap<_, _>>();
+
+    let missing = redirects
+        .iter()
+        .map(|(_, target)| target)
+        .filter(|target| !sources.contains_key(target))
+        .map(PageTitle::key)
+        .collect::<Vec<_>>();
+    let existing = Page::existing_titles(
+        &missing.iter().map(String::as_str).collect::<Vec<_>>(),
+        conn,
+    )?
+    .into_iter()
+    .map(|(namespace, title)| PageTitle::from_stored(namespace, &title))
+    .collect::<HashSet<_>>();
+
+    let entries = redirects
+        .iter()
+        .map(|(source, target)| {
+            json!({
+                "source": source.to_json(),
+                "target": target.to_json(),
+                "status": RedirectStatus::of(source, &sources, &existing).as_text(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    render_page(
+        app,
+        "special/redirects",
+        json!({
+            "title": request.title.to_json(),
+            "redirects": entries,
+        }),
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectStatus {
+    Ok,
+    Broken,
+    Double,
+    Loop,
+}
+
+impl RedirectStatus {
+    /// Classifies the redirect at `source` by walking the redirect chain starting from it.
+    fn of(
+        source: &PageTitle,
+        sources: &HashMap<&PageTitle, &PageTitle>,
+        existing: &HashSet<PageTitle>,
+    ) -> Self {
+        let target = sources[source];
+        if !sources.contains_key(target) {
+            return if existing.contains(target) {
+                Self::Ok
+            } else {
+                Self::Broken
+            };
+        }
+
+        let mut visited = HashSet::from([source]);
+        let mut current = target;
+        while let Some(next) = sources.get(current) {
+            if !visited.insert(current) {
+                return Self::Loop;
+            }
+            current = next;
+        }
+
+        Self::Double
+    }
+
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Broken => "broken",
+            Self::Double => "double",
+            Self::Loop => "loop",
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use axum::response::Response;
+use serde_json::json;
+
+use super::{Paging, SpecialRequest};
+use crate::{
+    controllers::wiki::page::{render_page, DATE_FORMAT},
+    model::report::{CachedReport, Report},
+    title::PageTitle,
+    App, Error,
+};
+
+pub(super) fn view_orphaned(app: &App, request: &SpecialRequest) -> Result<Response, Error> {
+    view_report(app, request, Report::Orphaned)
+}
+
+pub(super) fn view_dead_end(app: &App, request: &SpecialRequest) -> Result<Response, Error> {
+    view_report(app, request, Report::DeadEnd)
+}
+
+pub(super) fn view_wanted(app: &App, request: &SpecialRequest) -> Result<Response, Error> {
+    view_report(app, request, Report::Wanted)
+}
+
+/// Lists a page of the cached entries of a maintenance report.
+fn view_report(app: &App, request: &SpecialRequest, report: Report) -> Result<Response, Error> {
+    let paging = request.query::<Paging>()?;
+
+    let conn = &mut app.db.pool.get()?;
+    let mut entries = CachedReport::list(report, paging.offset(), paging.limit() + 1, conn)?;
+    let paging = paging.split(request, &mut entries);
+
+    render_page(
+        app,
+        "special/report",
+        json!({
+            "title": request.title.to_json(),
+            "report": report.as_text(),
+            "computed_on": CachedReport::computed_on(report, conn)?
+                .map(|computed_on| computed_on.format(DATE_FORMAT).to_string()),
+            "entries": entries
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "title": PageTitle::from_stored(entry.namespace, &entry.title).to_json(),
+                        "value": entry.value,
+                    })
+                })
+                .collect::<Vec<_>>(),
+            "paging": paging,
+        }),
+    )
+}
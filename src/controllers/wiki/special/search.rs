@@ -0,0 +1,149 @@
+use axum::response::{IntoResponse, Redirect, Response};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{Paging, SpecialRequest};
+use crate::{
+    controllers::wiki::page::render_page,
+    model::{
+        page::Page,
+        search::{PageFormula, PageSearch, MATCH_END, MATCH_START},
+    },
+    title::PageTitle,
+    App, Error,
+};
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    search: String,
+    #[serde(default)]
+    mode: SearchMode,
+    /// Set to list results even if a page with the searched title exists.
+    #[serde(default)]
+    fulltext: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SearchMode {
+    /// Searches the words of page titles and text.
+    #[default]
+    Text,
+    /// Searches the formulas in page text by their structure, written in TeX.
+    Formula,
+}
+
+impl SearchMode {
+    fn as_text(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Formula => "formula",
+        }
+    }
+}
+
+/// Searches the text or formulas of all pages, going directly to a page if its title was searched
+/// for.
+pub(super) fn view(app: &App, request: &SpecialRequest) -> Result<Response, Error> {
+    let query = request.query::<SearchQuery>()?;
+    let paging = request.query::<Paging>()?;
+    let search = query.search.trim();
+
+    let conn = &mut app.db.pool.get()?;
+    if search.is_empty() {
+        return render_search(app, request, &query, None, Vec::new(), None);
+    }
+    if query.mode == SearchMode::Formula {
+        let mut results = PageFormula::search(search, paging.offset(), paging.limit() + 1, conn)?;
+        let paging = paging.split(request, &mut results);
+        let results = results
+            .iter()
+            .map(|result| {
+                json!({
+                    "title": PageTitle::from_stored(result.namespace, &result.title).to_json(),
+                    "formula": result.source,
+                    "exact": result.exact,
+                    "similarity": (result.similarity * 100.0).round(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        return render_search(app, request, &query, None, results, Some(paging));
+    }
+
+    // Offer to create the searched title if it is valid and does not exist yet.
+    let mut missing = None;
+    if let Ok(title) = PageTitle::parse(search, &app.config.titles) {
+        if !title.namespace.is_special() {
+            if Page::by_title(title.namespace, &title.key(), conn)?.is_none() {
+                missing = Some(title);
+            } else if query.fulltext.is_none() {
+                return Ok(Redirect::to(&format!("/w/page/{}", title.query())).into_response());
+            }
+        }
+    }
+
+    let mut results = PageSearch::search(search, paging.offset(), paging.limit() + 1, conn)?;
+    let paging = paging.split(request, &mut results);
+    let results = results
+        .iter()
+        .map(|result| {
+            json!({
+                "title": PageTitle::from_stored(result.namespace, &result.title).to_json(),
+                "snippet": highlight(&result.snippet),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    render_search(app, request, &query, missing, results, Some(paging))
+}
+
+fn render_search(
+    app: &App,
+    request: &SpecialRequest,
+    query: &SearchQuery,
+    missing: Option<PageTitle>,
+    results: Vec<serde_json::Value>,
+    paging: Option<serde_json::Value>,
+) -> Result<Response, Error> {
+    render_page(
+        app,
+        "special/search",
+        json!({
+            "title": request.title.to_json(),
+            "search": query.search.trim(),
+            "mode": query.mode.as_text(),
+            "missing": missing.map(|title| title.to_json()),
+            "results": results,
+            "paging": paging,
+        }),
+    )
+}
+
+/// Escapes a search snippet as HTML, turning its match markers into `<mark>` elements.
+fn highlight(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+
+    html
+}
+
+#[test]
+fn highlight_test() {
+    assert_eq!(
+        highlight("a <b> \u{e000}triangle\u{e001} & more"),
+        "a &lt;b&gt; <mark>triangle</mark> &amp; more"
+    );
+}
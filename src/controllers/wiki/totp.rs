@@ -0,0 +1,166 @@
+use axum::{
+    debug_handler,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use chrono::Utc;
+use diesel::{connection::LoadConnection, pg::Pg, Connection};
+use serde::Deserialize;
+use serde_json::json;
+use tera::Context;
+
+use crate::{
+    controllers::wiki::{csrf::Csrf, viewer::LoggedIn},
+    model::{
+        totp::{RecoveryCode, UserTotp},
+        user::User,
+    },
+    App, AppState, Error,
+};
+
+#[debug_handler(state = AppState)]
+pub async fn get(
+    AppState(app): AppState,
+    LoggedIn { user, .. }: LoggedIn,
+) -> Result<Response, Error> {
+    let conn = &mut app.db.pool.get()?;
+    render_current(&app, &user, None, conn)
+}
+
+#[debug_handler(state = AppState)]
+pub async fn post(
+    AppState(app): AppState,
+    LoggedIn { user, .. }: LoggedIn,
+    Csrf(Form(data)): Csrf<Form<TotpData>>,
+) -> Result<Response, Error> {
+    let conn = &mut app.db.pool.get()?;
+
+    let now = Utc::now();
+    let Some(totp) = UserTotp::by_user_id(user.id, conn)? else {
+        return render_current(&app, &user, None, conn);
+    };
+
+    if !totp.is_enabled() {
+        // enrolling is only finished once the app is shown to produce the right codes
+        if !totp.verify(&data.code, now, conn)? {
+            return render_current(&app, &user, Some(TotpError::WrongCode), conn);
+        }
+        let codes = conn.transaction::<_, Error, _>(|conn| {
+            _ = totp.enable(now, conn)?;
+            RecoveryCode::generate_all(user.id, &app.config.passwords, conn)
+        })?;
+
+        return render_totp(&app, TotpStage::Codes, None, json!({ "codes": codes }));
+    }
+
+    // changing an enabled code requires a code as well, so that a stolen session is not enough
+    let valid = totp.verify(&data.code, now, conn)?
+        || RecoveryCode::redeem(user.id, &data.code, &app.config.passwords, now, conn)?;
+    if !valid {
+        return render_current(&app, &user, Some(TotpError::WrongCode), conn);
+    }
+
+    if data.disable.is_some() {
+        _ = UserTotp::delete_by_user_id(user.id, conn)?;
+        return render_totp(&app, TotpStage::Disabled, None, json!({}));
+    }
+
+    let codes = RecoveryCode::generate_all(user.id, &app.config.passwords, conn)?;
+    render_totp(&app, TotpStage::Codes, None, json!({ "codes": codes }))
+}
+
+/// Renders the page for the current state of the code of `user`, starting the enrollment of a
+/// new secret if there is none.
+fn render_current<C>(
+    app: &App,
+    user: &User,
+    error: Option<TotpError>,
+    conn: &mut C,
+) -> Result<Response, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let totp = match UserTotp::by_user_id(user.id, conn)? {
+        Some(totp) => totp,
+        None => UserTotp::enroll(user.id, conn)?,
+    };
+
+    if totp.is_enabled() {
+        let remaining = RecoveryCode::count_unused(user.id, conn)?;
+        return render_totp(
+            app,
+            TotpStage::Enabled,
+            error,
+            json!({ "remaining": remaining }),
+        );
+    }
+
+    let generator = totp.totp();
+    render_totp(
+        app,
+        TotpStage::Enroll,
+        error,
+        json!({
+            "qr": generator.qr_svg(&app.config.title, &user.name),
+            "uri": generator.uri(&app.config.title, &user.name),
+            "secret": generator.secret_base32(),
+        }),
+    )
+}
+
+fn render_totp(
+    app: &App,
+    stage: TotpStage,
+    error: Option<TotpError>,
+    extra: serde_json::Value,
+) -> Result<Response, Error> {
+    let status = match &error {
+        Some(_) => StatusCode::FORBIDDEN,
+        None => StatusCode::OK,
+    };
+    let mut context = Context::from_serialize(extra)?;
+    context.insert("stage", stage.name());
+    context.insert("error", &error.as_ref().map(ToString::to_string));
+    let html = app.renderer.render("totp", &context)?;
+
+    Ok((status, Html::from(html)).into_response())
+}
+
+/// A code confirming the enrollment, or confirming that the recovery codes are replaced or the
+/// code is disabled.
+#[derive(Deserialize)]
+pub struct TotpData {
+    pub code: String,
+    #[serde(default)]
+    pub disable: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TotpStage {
+    /// Showing the secret to enter into an authenticator app, with a form to confirm it.
+    Enroll,
+    /// Showing the recovery codes, which are not shown again.
+    Codes,
+    /// Showing that the code is enabled, with forms to replace the recovery codes or disable it.
+    Enabled,
+    /// Telling that the code was disabled.
+    Disabled,
+}
+
+impl TotpStage {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Enroll => "enroll",
+            Self::Codes => "codes",
+            Self::Enabled => "enabled",
+            Self::Disabled => "disabled",
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TotpError {
+    #[error("the code is wrong or was already used")]
+    WrongCode,
+}
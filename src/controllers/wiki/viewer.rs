@@ -0,0 +1,173 @@
+use axum::{
+    extract::{FromRequestParts, Request},
+    http::{header, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use diesel::{connection::LoadConnection, pg::Pg, Connection};
+
+use crate::{
+    auth::{Permission, Permissions},
+    controllers::wiki::login::{removal_cookie, session_cookie, SESSION_COOKIE},
+    model::{
+        group::UserGroup,
+        user::{Session, User},
+    },
+    App, AppState, Error,
+};
+
+/// The user making a request along with their permissions, or an anonymous visitor.
+///
+/// Loaded once per request by [`load_viewer`], and extracted by handlers to check what the
+/// requester may do.
+#[derive(Clone)]
+pub struct Viewer {
+    pub session: Option<Session>,
+    pub user: Option<User>,
+    pub permissions: Permissions,
+}
+
+impl Viewer {
+    fn anonymous(app: &App) -> Self {
+        Self {
+            session: None,
+            user: None,
+            permissions: app.config.permissions.resolve(None::<[&str; 0]>),
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        self.permissions.has(permission)
+    }
+
+    /// Returns the logged in user if they have `permission`.
+    pub fn user_with(&self, permission: Permission) -> Option<&User> {
+        self.user.as_ref().filter(|_| self.can(permission))
+    }
+}
+
+impl FromRequestParts<AppState> for Viewer {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        // routes outside of the wiki are not behind the middleware, and only see visitors
+        Ok(parts
+            .extensions
+            .get::<Self>()
+            .cloned()
+            .unwrap_or_else(|| Self::anonymous(state)))
+    }
+}
+
+/// Extractor for pages which require logging in, redirecting to the login page otherwise.
+pub struct LoggedIn {
+    pub session: Session,
+    pub user: User,
+    pub permissions: Permissions,
+}
+
+impl FromRequestParts<AppState> for LoggedIn {
+    type Rejection = Response;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        match Viewer::from_request_parts(parts, state).await? {
+            Viewer {
+                session: Some(session),
+                user: Some(user),
+                permissions,
+            } => Ok(Self {
+                session,
+                user,
+                permissions,
+            }),
+            _ => Err(Redirect::to("/w/login").into_response()),
+        }
+    }
+}
+
+/// Middleware loading the [`Viewer`] of the request, along with the permissions of their groups.
+pub async fn load_viewer(
+    AppState(app): AppState,
+    mut jar: CookieJar,
+    mut request: Request,
+    next: Next,
+) -> Result<(CookieJar, Response), Error> {
+    let original = jar.clone();
+    let viewer = {
+        let conn = &mut app.db.pool.get()?;
+        match validate_login(&app, &mut jar, conn)? {
+            Some(session) => match User::by_id(session.user_id, conn)? {
+                Some(user) => {
+                    let groups = UserGroup::names_by_user_id(user.id, conn)?;
+                    Viewer {
+                        permissions: app
+                            .config
+                            .permissions
+                            .resolve(Some(groups.iter().map(String::as_str))),
+                        session: Some(session),
+                        user: Some(user),
+                    }
+                }
+                None => Viewer::anonymous(&app),
+            },
+            None => Viewer::anonymous(&app),
+        }
+    };
+    _ = request.extensions_mut().insert(viewer);
+
+    let response = next.run(request).await;
+    // a handler logging in or out has the last word on the session cookie
+    let sets_session = response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .any(|value| value.as_bytes().starts_with(SESSION_COOKIE.as_bytes()));
+    if sets_session {
+        return Ok((original, response));
+    }
+
+    Ok((jar, response))
+}
+
+/// Loads the session of the logged in user, if any, extending it on activity.
+///
+/// Expired and unknown sessions are removed along with their cookie, and renewed sessions get a
+/// new cookie expiring along with them.
+fn validate_login<C>(app: &App, jar: &mut CookieJar, conn: &mut C) -> Result<Option<Session>, Error>
+where
+    C: Connection<Backend = Pg> + LoadConnection,
+{
+    let Some(cookie) = jar.get(SESSION_COOKIE) else {
+        return Ok(None);
+    };
+    let config = &app.config.sessions;
+    let token = cookie.value().to_string();
+    let Some(mut session) = Session::from_token(&token, config, conn)? else {
+        // the session is gone, such as one from before tokens were hashed, so the browser
+        // should stop sending its cookie
+        *jar = jar.clone().remove(removal_cookie(config));
+        return Ok(None);
+    };
+
+    let now = Utc::now();
+    if session.is_expired(now) {
+        _ = session.delete(conn)?;
+        *jar = jar.clone().remove(removal_cookie(config));
+        return Ok(None);
+    }
+    if session.renew(config, now, conn)? {
+        *jar = jar
+            .clone()
+            .add(session_cookie(config, &session, &token, now));
+    }
+
+    Ok(Some(session))
+}
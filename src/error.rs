@@ -6,16 +6,29 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error(transparent)]
     Env(#[from] std::env::VarError),
+    #[error("{0} must be set {1}")]
+    MissingConfig(&'static str, &'static str),
 
     #[error(transparent)]
     Dotenvy(#[from] dotenvy::Error),
 
     #[error(transparent)]
     Http(#[from] axum::http::Error),
+    #[error(transparent)]
+    Form(#[from] serde_html_form::de::Error),
 
     #[error(transparent)]
     Tera(#[from] tera::Error),
 
+    #[error(transparent)]
+    Hash(#[from] crate::auth::hash::HashError),
+    #[error(transparent)]
+    Password(#[from] crate::auth::PasswordError),
+    #[error(transparent)]
+    Mail(#[from] crate::mail::MailError),
+    #[error(transparent)]
+    Permission(#[from] crate::auth::PermissionError),
+
     #[error(transparent)]
     Pool(#[from] diesel::r2d2::PoolError),
     #[error(transparent)]
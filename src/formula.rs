@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+/// Commands which only affect the presentation of a formula, and are dropped when normalising.
+const PRESENTATION_COMMANDS: &[&str] = &[
+    "left",
+    "right",
+    "big",
+    "Big",
+    "bigg",
+    "Bigg",
+    "bigl",
+    "bigr",
+    "Bigl",
+    "Bigr",
+    "displaystyle",
+    "textstyle",
+    "scriptstyle",
+    "limits",
+    "nolimits",
+    "quad",
+    "qquad",
+];
+
+/// Commands which are written differently but mean the same, as `(alias, canonical)` pairs.
+const COMMAND_ALIASES: &[(&str, &str)] = &[
+    ("dfrac", "frac"),
+    ("tfrac", "frac"),
+    ("le", "leq"),
+    ("ge", "geq"),
+    ("ne", "neq"),
+    ("to", "rightarrow"),
+    ("lbrace", "{"),
+    ("rbrace", "}"),
+];
+
+/// Greek letters, which are variables like latin letters rather than operators.
+const GREEK_LETTERS: &[&str] = &[
+    "alpha",
+    "beta",
+    "gamma",
+    "delta",
+    "epsilon",
+    "varepsilon",
+    "zeta",
+    "eta",
+    "theta",
+    "vartheta",
+    "iota",
+    "kappa",
+    "lambda",
+    "mu",
+    "nu",
+    "xi",
+    "rho",
+    "varrho",
+    "sigma",
+    "tau",
+    "upsilon",
+    "phi",
+    "varphi",
+    "chi",
+    "psi",
+    "omega",
+    "Gamma",
+    "Delta",
+    "Theta",
+    "Lambda",
+    "Xi",
+    "Sigma",
+    "Upsilon",
+    "Phi",
+    "Psi",
+    "Omega",
+];
+
+/// The longest sequences of tokens indexed for formula search.
+const MAX_NGRAM: usize = 3;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    /// A variable, renamed by order of appearance when normalising.
+    Variable(String),
+    /// An operator, command, number or delimiter, kept as written.
+    Symbol(String),
+}
+
+/// Converts a TeX formula into a sequence of tokens describing its structure.
+///
+/// Variables are replaced by `$1`, `$2`, ... in order of their first appearance, so that formulas
+/// which differ only in the names of their variables have the same tokens. Spacing and sizing
+/// commands are dropped, and braces around a single token are removed, so that `x^{2}` and `x^2`
+/// are the same.
+pub fn normalize(source: &str) -> Vec<String> {
+    let mut variables = HashMap::new();
+    simplify_groups(lex(source))
+        .into_iter()
+        .map(|token| match token {
+            Token::Variable(name) => {
+                let next = variables.len() + 1;
+                format!("${}", variables.entry(name).or_insert(next))
+            }
+            Token::Symbol(symbol) => symbol,
+        })
+        .collect()
+}
+
+/// Returns the distinct runs of two to three consecutive tokens, as indexed for formula search.
+///
+/// Variables are renumbered within each run, so that a part of a formula matches the same part of
+/// another regardless of the variables preceding it. A formula of a single token is its own n-gram.
+pub fn ngrams(tokens: &[String]) -> Vec<String> {
+    if tokens.len() < 2 {
+        return tokens.to_vec();
+    }
+
+    let mut ngrams = (2..=MAX_NGRAM.min(tokens.len()))
+        .flat_map(|n| tokens.windows(n).map(renumber_variables))
+        .collect::<Vec<_>>();
+    ngrams.sort_unstable();
+    ngrams.dedup();
+
+    ngrams
+}
+
+/// Joins normalised tokens with spaces, numbering their variables from `$1` again.
+fn renumber_variables(tokens: &[String]) -> String {
+    let mut variables = HashMap::new();
+    tokens
+        .iter()
+        .map(|token| match token.strip_prefix('$') {
+            Some(variable) => {
+                let next = variables.len() + 1;
+                format!("${}", variables.entry(variable).or_insert(next))
+            }
+            None => token.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn lex(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '\\' => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                    name.push(c);
+                    chars.next();
+                }
+
+                if name.is_empty() {
+                    match chars.next() {
+                        // spacing
+                        Some(',' | ';' | ':' | '!' | ' ') | None => {}
+                        Some(c) => tokens.push(Token::Symbol(format!("\\{c}"))),
+                    }
+                    continue;
+                }
+                if PRESENTATION_COMMANDS.contains(&name.as_str()) {
+                    continue;
+                }
+
+                if let Some((_, canonical)) =
+                    COMMAND_ALIASES.iter().find(|(alias, _)| *alias == name)
+                {
+                    name = canonical.to_string();
+                }
+                tokens.push(if GREEK_LETTERS.contains(&name.as_str()) {
+                    Token::Variable(format!("\\{name}"))
+                } else {
+                    Token::Symbol(format!("\\{name}"))
+                });
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                    number.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Symbol(number));
+            }
+            c if c.is_alphabetic() => tokens.push(Token::Variable(c.to_string())),
+            c => tokens.push(Token::Symbol(c.to_string())),
+        }
+    }
+
+    tokens
+}
+
+/// Removes braces which group a single token or nothing at all.
+fn simplify_groups(tokens: Vec<Token>) -> Vec<Token> {
+    let open = || Token::Symbol("{".to_string());
+    let mut simplified = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        if token == Token::Symbol("}".to_string()) {
+            let len = simplified.len();
+            if len >= 1 && simplified[len - 1] == open() {
+                simplified.pop();
+                continue;
+            }
+            if len >= 2 && simplified[len - 2] == open() {
+                simplified.remove(len - 2);
+                continue;
+            }
+        }
+        simplified.push(token);
+    }
+
+    simplified
+}
+
+#[test]
+fn normalize_test() {
+    assert_eq!(
+        normalize(r"\int_a^b f(x)\,dx"),
+        ["\\int", "_", "$1", "^", "$2", "$3", "(", "$4", ")", "$5", "$4"]
+    );
+    assert_eq!(
+        normalize(r"\int_{p}^{q} g(t) \, dt"),
+        normalize(r"\int_a^b f(x)\,dx")
+    );
+    assert_eq!(
+        normalize(r"\left( \alpha^{2} + \beta^2 \right)"),
+        normalize(r"(x^2 + y^2)")
+    );
+    assert_eq!(normalize(r"\dfrac{1}{n}"), ["\\frac", "1", "$1"]);
+    assert_ne!(normalize("x^2"), normalize("x^3"));
+
+    let tokens = normalize("a+b");
+    assert_eq!(ngrams(&tokens), ["$1 +", "$1 + $2", "+ $1"]);
+    assert!(ngrams(&normalize(r"\int_0^1 g(t)\,dt")).contains(&"$1 ( $2".to_string()));
+    assert_eq!(ngrams(&normalize("x")), ["$1"]);
+}
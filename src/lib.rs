@@ -4,12 +4,15 @@ pub mod auth;
 pub mod controllers;
 pub mod db;
 mod error;
+pub mod formula;
+pub mod mail;
 pub mod model;
 pub mod output;
 pub mod render;
 mod router;
 pub mod schema;
 mod tasks;
+pub mod title;
 
 pub use self::{
     app::{App, AppState},
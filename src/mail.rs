@@ -0,0 +1,261 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::Utc;
+use rand_chacha::ChaCha12Rng;
+use rand_core::{RngCore, SeedableRng};
+
+/// How e-mail is sent, and from which address.
+#[derive(Clone)]
+pub struct MailConfig {
+    pub from: String,
+    pub transport: Arc<dyn Transport>,
+}
+
+impl MailConfig {
+    pub fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), MailError> {
+        self.transport.send(&Message {
+            from: self.from.clone(),
+            to: to.to_string(),
+            subject: subject.to_string(),
+            body: body.to_string(),
+        })
+    }
+}
+
+/// A plain text e-mail.
+pub struct Message {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Message {
+    /// Formats the message with its headers, with lines ending in CRLF as required on the wire.
+    pub fn encode(&self) -> Result<String, MailError> {
+        for header in [&self.from, &self.to, &self.subject] {
+            // a line break would allow injecting further headers
+            if header.contains(['\r', '\n']) {
+                return Err(MailError::Header(header.clone()));
+            }
+        }
+
+        let mut encoded = format!(
+            "From: <{}>\r\nTo: <{}>\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+             Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+            self.from,
+            self.to,
+            encode_header(&self.subject),
+            Utc::now().to_rfc2822(),
+        );
+        for line in self.body.lines() {
+            encoded.push_str(line);
+            encoded.push_str("\r\n");
+        }
+
+        Ok(encoded)
+    }
+}
+
+/// Encodes a header value as RFC 2047 encoded-words unless it is plain ASCII, as headers must be.
+///
+/// Each word holds at most 45 bytes of whole characters, which keeps it within the limit of 75
+/// characters per word, and words are folded onto continuation lines.
+fn encode_header(value: &str) -> String {
+    const MAX_WORD_BYTES: usize = 45;
+
+    if value.is_ascii() {
+        return value.to_string();
+    }
+
+    let mut words = Vec::new();
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        if index + c.len_utf8() - start > MAX_WORD_BYTES {
+            words.push(&value[start..index]);
+            start = index;
+        }
+    }
+    words.push(&value[start..]);
+
+    words
+        .into_iter()
+        .map(|word| format!("=?UTF-8?B?{}?=", BASE64_STANDARD.encode(word)))
+        .collect::<Vec<_>>()
+        .join("\r\n ")
+}
+
+/// A way of delivering messages.
+pub trait Transport: Send + Sync {
+    fn send(&self, message: &Message) -> Result<(), MailError>;
+}
+
+/// Writes every message to a file in a directory instead of sending it, for development and
+/// testing.
+pub struct OutboxTransport {
+    pub dir: PathBuf,
+}
+
+impl OutboxTransport {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl Transport for OutboxTransport {
+    fn send(&self, message: &Message) -> Result<(), MailError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let name = format!(
+            "{}-{:08x}.eml",
+            Utc::now().format("%Y%m%d%H%M%S%6f"),
+            ChaCha12Rng::from_os_rng().next_u32()
+        );
+
+        Ok(std::fs::write(self.dir.join(name), message.encode()?)?)
+    }
+}
+
+/// Sends messages to an SMTP server without encryption or authentication, such as a local relay
+/// or test server.
+pub struct SmtpTransport {
+    /// The address of the server as `host:port`.
+    pub address: String,
+}
+
+impl SmtpTransport {
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn new(address: String) -> Self {
+        Self { address }
+    }
+}
+
+impl Transport for SmtpTransport {
+    fn send(&self, message: &Message) -> Result<(), MailError> {
+        let data = message.encode()?;
+
+        let stream = TcpStream::connect(&self.address)?;
+        stream.set_read_timeout(Some(Self::TIMEOUT))?;
+        stream.set_write_timeout(Some(Self::TIMEOUT))?;
+        let mut session = SmtpSession {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+
+        session.expect(220)?;
+        session.command("EHLO localhost", 250)?;
+        session.command(&format!("MAIL FROM:<{}>", message.from), 250)?;
+        session.command(&format!("RCPT TO:<{}>", message.to), 250)?;
+        session.command("DATA", 354)?;
+        session.command(&format!("{}.", dot_stuff(&data)), 250)?;
+        session.command("QUIT", 221)
+    }
+}
+
+struct SmtpSession {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl SmtpSession {
+    fn command(&mut self, command: &str, code: u16) -> Result<(), MailError> {
+        self.writer.write_all(command.as_bytes())?;
+        self.writer.write_all(b"\r\n")?;
+        self.expect(code)
+    }
+
+    /// Reads a reply, which may span several lines, and checks its code.
+    fn expect(&mut self, code: u16) -> Result<(), MailError> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(MailError::Reply("connection closed".to_string()));
+            }
+            let line = line.trim_end();
+
+            if line.get(..3).and_then(|reply| reply.parse().ok()) != Some(code) {
+                return Err(MailError::Reply(line.to_string()));
+            }
+            // continuation lines have a dash after the code
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Doubles leading dots, so that no line of the message ends the data early.
+fn dot_stuff(data: &str) -> String {
+    data.split_inclusive("\r\n")
+        .map(|line| {
+            if line.starts_with('.') {
+                format!(".{line}")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MailError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("the mail server replied '{0}'")]
+    Reply(String),
+    #[error("the header '{0}' contains a line break")]
+    Header(String),
+}
+
+#[test]
+fn mail_test() {
+    let message = Message {
+        from: "wiki@example.org".to_string(),
+        to: "euclid@example.org".to_string(),
+        subject: "Elements".to_string(),
+        body: "Book I\n.\n..done".to_string(),
+    };
+    let encoded = message.encode().unwrap();
+    assert!(encoded.starts_with("From: <wiki@example.org>\r\nTo: <euclid@example.org>\r\n"));
+    assert!(encoded.ends_with("\r\n\r\nBook I\r\n.\r\n..done\r\n"));
+    assert!(dot_stuff(&encoded).ends_with("\r\n\r\nBook I\r\n..\r\n...done\r\n"));
+
+    let injected = Message {
+        to: "euclid@example.org\r\nBcc: everyone@example.org".to_string(),
+        ..message
+    };
+    assert!(matches!(injected.encode(), Err(MailError::Header(_))));
+}
+
+#[test]
+fn encode_header_test() {
+    assert_eq!(encode_header("Elements"), "Elements");
+    assert_eq!(encode_header("Éléments"), "=?UTF-8?B?w4lsw6ltZW50cw==?=");
+
+    // long values are split between characters into words of at most 75 characters
+    let long = "Στοιχεῖα ".repeat(10);
+    let encoded = encode_header(&long);
+    assert!(encoded.is_ascii());
+    let words = encoded.split("\r\n ").collect::<Vec<_>>();
+    assert!(words.len() > 1);
+    assert!(words.iter().all(|word| word.len() <= 75));
+    let decoded = words
+        .iter()
+        .map(|word| {
+            let word = word
+                .strip_prefix("=?UTF-8?B?")
+                .unwrap()
+                .strip_suffix("?=")
+                .unwrap();
+            String::from_utf8(BASE64_STANDARD.decode(word).unwrap()).unwrap()
+        })
+        .collect::<String>();
+    assert_eq!(decoded, long);
+}
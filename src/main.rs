@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use euclidon::{app::Config, App, Error};
 use tokio::net::TcpListener;
@@ -15,5 +15,9 @@ async fn main() -> Result<(), Error> {
     let listener = TcpListener::bind(server_url).await?;
 
     println!("> server listening on: {server_url}");
-    Ok(axum::serve(listener, router).await?)
+    Ok(axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?)
 }
@@ -0,0 +1,244 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use diesel::{
+    connection::LoadConnection, pg::Pg, Connection, ExpressionMethods, Insertable,
+    OptionalExtension, QueryDsl, Queryable, RunQueryDsl, Selectable,
+};
+
+use crate::{
+    model::{
+        namespace::Namespace,
+        page::{Page, Revision},
+    },
+    schema::{archived_pages, archived_revisions, pages, revisions, users},
+    Error,
+};
+
+/// A deleted page, kept so that it may later be restored.
+#[derive(Queryable, Selectable)]
+#[diesel(check_for_backend(Pg))]
+pub struct ArchivedPage {
+    pub id: i64,
+    pub page_id: i64,
+    pub title: String,
+
+    pub rev_id: i64,
+    pub root_id: i64,
+
+    pub created_on: DateTime<Utc>,
+
+    pub deleted_by: i64,
+    pub deleted_on: DateTime<Utc>,
+    pub reason: String,
+
+    pub namespace: Namespace,
+}
+
+impl ArchivedPage {
+    /// Returns the most recent deletion of the page with the given title, if any.
+    pub fn latest_by_title<C>(
+        namespace: Namespace,
+        title: &str,
+        conn: &mut C,
+    ) -> Result<Option<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(archived_pages::table
+            .filter(archived_pages::namespace.eq(namespace))
+            .filter(archived_pages::title.eq(title))
+            .order_by(archived_pages::deleted_on.desc())
+            .select(archived_pages::all_columns)
+            .first(conn)
+            .optional()?)
+    }
+
+    /// Deletes `page`, moving it and its revision chain into the archive tables.
+    ///
+    /// Contents are left untouched, so the archived revisions can be restored later on.
+    pub fn archive<C>(
+        page: Page,
+        deleted_by: i64,
+        reason: &str,
+        conn: &mut C,
+    ) -> Result<Self, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        conn.transaction(|conn| {
+            let revisions = Revision::chain(page.rev_id, conn)?;
+
+            let archive: Self = diesel::insert_into(archived_pages::table)
+                .values((
+                    archived_pages::page_id.eq(page.id),
+                    archived_pages::namespace.eq(page.namespace),
+                    archived_pages::title.eq(&page.title),
+                    archived_pages::rev_id.eq(page.rev_id),
+                    archived_pages::root_id.eq(page.root_id),
+                    archived_pages::created_on.eq(page.created_on),
+                    archived_pages::deleted_by.eq(deleted_by),
+                    archived_pages::deleted_on.eq(Utc::now()),
+                    archived_pages::reason.eq(reason),
+                ))
+                .returning(archived_pages::all_columns)
+                .get_result(conn)?;
+
+            diesel::insert_into(archived_revisions::table)
+                .values(
+                    revisions
+                        .iter()
+                        .map(|revision| ArchivedRevision::from_revision(revision, archive.id))
+                        .collect::<Vec<_>>(),
+                )
+                .execute(conn)?;
+
+            diesel::delete(pages::table.filter(pages::id.eq(page.id))).execute(conn)?;
+            diesel::delete(
+                revisions::table
+                    .filter(revisions::id.eq_any(revisions.iter().map(|revision| revision.id))),
+            )
+            .execute(conn)?;
+
+            Ok(archive)
+        })
+    }
+
+    /// Restores the archived revisions with the given ids as the page `title`.
+    ///
+    /// The selected revisions are relinked into a single chain in order of creation, and archives
+    /// left without any revisions are removed. Returns `None` if the page currently exists or none
+    /// of the ids belong to an archived revision of it.
+    pub fn restore<C>(
+        namespace: Namespace,
+        title: &str,
+        ids: &[i64],
+        conn: &mut C,
+    ) -> Result<Option<Page>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        conn.transaction(|conn| {
+            if Page::by_title(namespace, title, conn)?.is_some() {
+                return Ok(None);
+            }
+
+            let selected: Vec<ArchivedRevision> = archived_revisions::table
+                .inner_join(archived_pages::table)
+                .filter(archived_pages::namespace.eq(namespace))
+                .filter(archived_pages::title.eq(title))
+                .filter(archived_revisions::id.eq_any(ids))
+                .order_by((
+                    archived_revisions::created_on.asc(),
+                    archived_revisions::id.asc(),
+                ))
+                .select(archived_revisions::all_columns)
+                .load(conn)?;
+            let (Some(first), Some(last)) = (selected.first(), selected.last()) else {
+                return Ok(None);
+            };
+
+            let page_id: i64 = archived_pages::table
+                .filter(archived_pages::id.eq(last.archive_id))
+                .select(archived_pages::page_id)
+                .get_result(conn)?;
+
+            let mut parent_id = None;
+            for revision in &selected {
+                diesel::insert_into(revisions::table)
+                    .values((
+                        revisions::id.eq(revision.id),
+                        revisions::parent_id.eq(parent_id),
+                        revisions::content_id.eq(revision.content_id),
+                        revisions::user_id.eq(revision.user_id),
+                        revisions::created_on.eq(revision.created_on),
+                    ))
+                    .execute(conn)?;
+                parent_id = Some(revision.id);
+            }
+
+            let page = diesel::insert_into(pages::table)
+                .values((
+                    pages::id.eq(page_id),
+                    pages::namespace.eq(namespace),
+                    pages::title.eq(title),
+                    pages::rev_id.eq(last.id),
+                    pages::root_id.eq(first.id),
+                    pages::created_on.eq(first.created_on),
+                ))
+                .returning(pages::all_columns)
+                .get_result(conn)?;
+
+            diesel::delete(archived_revisions::table.filter(
+                archived_revisions::id.eq_any(selected.iter().map(|revision| revision.id)),
+            ))
+            .execute(conn)?;
+
+            let archives = selected
+                .iter()
+                .map(|revision| revision.archive_id)
+                .collect::<HashSet<_>>();
+            for archive_id in archives {
+                let remaining = archived_revisions::table
+                    .filter(archived_revisions::archive_id.eq(archive_id))
+                    .count()
+                    .get_result::<i64>(conn)?;
+                if remaining == 0 {
+                    diesel::delete(archived_pages::table.filter(archived_pages::id.eq(archive_id)))
+                        .execute(conn)?;
+                }
+            }
+
+            Ok(Some(page))
+        })
+    }
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = archived_revisions, check_for_backend(Pg))]
+pub struct ArchivedRevision {
+    pub id: i64,
+    pub archive_id: i64,
+    pub parent_id: Option<i64>,
+    pub content_id: i64,
+
+    pub user_id: i64,
+    pub created_on: DateTime<Utc>,
+}
+
+impl ArchivedRevision {
+    fn from_revision(revision: &Revision, archive_id: i64) -> Self {
+        Self {
+            id: revision.id,
+            archive_id,
+            parent_id: revision.parent_id,
+            content_id: revision.content_id,
+
+            user_id: revision.user_id,
+            created_on: revision.created_on,
+        }
+    }
+
+    /// Lists all archived revisions of the page along with their authors' names, newest
+    /// first.
+    pub fn by_title<C>(
+        namespace: Namespace,
+        title: &str,
+        conn: &mut C,
+    ) -> Result<Vec<(Self, String)>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(archived_revisions::table
+            .inner_join(archived_pages::table)
+            .inner_join(users::table)
+            .filter(archived_pages::namespace.eq(namespace))
+            .filter(archived_pages::title.eq(title))
+            .order_by((
+                archived_revisions::created_on.desc(),
+                archived_revisions::id.desc(),
+            ))
+            .select((archived_revisions::all_columns, users::name))
+            .load(conn)?)
+    }
+}
@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use diesel::{
+    connection::LoadConnection, pg::Pg, Connection, ExpressionMethods, Insertable,
+    OptionalExtension, QueryDsl, Queryable, RunQueryDsl, Selectable,
+};
+
+use crate::{
+    model::namespace::Namespace,
+    schema::{discussion_posts, discussion_threads, users},
+    Error,
+};
+
+/// A discussion thread on a talk page, started with a subject and an opening post.
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = discussion_threads, check_for_backend(Pg))]
+pub struct Thread {
+    pub id: i64,
+    pub namespace: Namespace,
+    pub title: String,
+    pub subject: String,
+
+    pub user_id: i64,
+    pub created_on: DateTime<Utc>,
+}
+
+impl Thread {
+    pub fn by_id<C>(id: i64, conn: &mut C) -> Result<Option<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(discussion_threads::table
+            .find(id)
+            .select(discussion_threads::all_columns)
+            .first(conn)
+            .optional()?)
+    }
+
+    /// Lists the threads of a talk page, oldest first, along with the name of their author.
+    pub fn by_title<C>(
+        namespace: Namespace,
+        title: &str,
+        conn: &mut C,
+    ) -> Result<Vec<(Self, String)>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(discussion_threads::table
+            .inner_join(users::table)
+            .filter(discussion_threads::namespace.eq(namespace))
+            .filter(discussion_threads::title.eq(title))
+            .order_by((discussion_threads::created_on, discussion_threads::id))
+            .select((discussion_threads::all_columns, users::name))
+            .load(conn)?)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = discussion_threads, check_for_backend(Pg))]
+pub struct NewThread<'a> {
+    pub namespace: Namespace,
+    pub title: &'a str,
+    pub subject: &'a str,
+    pub user_id: i64,
+}
+
+impl<'a> NewThread<'a> {
+    pub fn new(namespace: Namespace, title: &'a str, subject: &'a str, user_id: i64) -> Self {
+        Self {
+            namespace,
+            title,
+            subject,
+            user_id,
+        }
+    }
+
+    pub fn insert<C>(self, conn: &mut C) -> Result<Thread, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(diesel::insert_into(discussion_threads::table)
+            .values(self)
+            .returning(discussion_threads::all_columns)
+            .get_result(conn)?)
+    }
+}
+
+/// A single signed post in a discussion thread, optionally in reply to another post.
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = discussion_posts, check_for_backend(Pg))]
+pub struct Post {
+    pub id: i64,
+    pub thread_id: i64,
+    pub parent_id: Option<i64>,
+    pub body: String,
+
+    pub user_id: i64,
+    pub created_on: DateTime<Utc>,
+}
+
+impl Post {
+    pub fn by_id<C>(id: i64, conn: &mut C) -> Result<Option<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(discussion_posts::table
+            .find(id)
+            .select(discussion_posts::all_columns)
+            .first(conn)
+            .optional()?)
+    }
+
+    /// Lists the posts of the given threads, oldest first, along with the name of their author.
+    pub fn by_thread_ids<C>(thread_ids: &[i64], conn: &mut C) -> Result<Vec<(Self, String)>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(discussion_posts::table
+            .inner_join(users::table)
+            .filter(discussion_posts::thread_id.eq_any(thread_ids))
+            .order_by((discussion_posts::created_on, discussion_posts::id))
+            .select((discussion_posts::all_columns, users::name))
+            .load(conn)?)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = discussion_posts, check_for_backend(Pg))]
+pub struct NewPost<'a> {
+    pub thread_id: i64,
+    pub parent_id: Option<i64>,
+    pub body: &'a str,
+    pub user_id: i64,
+}
+
+impl<'a> NewPost<'a> {
+    pub fn new(thread_id: i64, parent_id: Option<i64>, body: &'a str, user_id: i64) -> Self {
+        Self {
+            thread_id,
+            parent_id,
+            body,
+            user_id,
+        }
+    }
+
+    pub fn insert<C>(self, conn: &mut C) -> Result<Post, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(diesel::insert_into(discussion_posts::table)
+            .values(self)
+            .returning(discussion_posts::all_columns)
+            .get_result(conn)?)
+    }
+}
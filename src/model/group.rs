@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use diesel::{
+    connection::LoadConnection, pg::Pg, Connection, ExpressionMethods, QueryDsl, Queryable,
+    RunQueryDsl, Selectable,
+};
+
+use crate::{schema::user_groups, Error};
+
+/// The membership of a user in a group, which grants the permissions configured for it.
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = user_groups, check_for_backend(Pg))]
+pub struct UserGroup {
+    pub user_id: i64,
+    pub group_name: String,
+    pub added_on: DateTime<Utc>,
+}
+
+impl UserGroup {
+    /// Lists the names of the groups a user was added to, without the implicit ones.
+    pub fn names_by_user_id<C>(user_id: i64, conn: &mut C) -> Result<Vec<String>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(user_groups::table
+            .filter(user_groups::user_id.eq(user_id))
+            .select(user_groups::group_name)
+            .order_by(user_groups::group_name)
+            .load(conn)?)
+    }
+
+    /// Adds a user to a group. Returns whether they were not in it yet.
+    pub fn add<C>(
+        user_id: i64,
+        group_name: &str,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(0 != diesel::insert_into(user_groups::table)
+            .values((
+                user_groups::user_id.eq(user_id),
+                user_groups::group_name.eq(group_name),
+                user_groups::added_on.eq(now),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?)
+    }
+
+    /// Removes a user from a group. Returns whether they were in it.
+    pub fn remove<C>(user_id: i64, group_name: &str, conn: &mut C) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(0 != diesel::delete(
+            user_groups::table
+                .filter(user_groups::user_id.eq(user_id))
+                .filter(user_groups::group_name.eq(group_name)),
+        )
+        .execute(conn)?)
+    }
+}
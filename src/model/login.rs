@@ -0,0 +1,213 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use diesel::{
+    connection::LoadConnection, dsl::count_star, pg::Pg, Connection, ExpressionMethods, QueryDsl,
+    Queryable, RunQueryDsl, Selectable,
+};
+
+use crate::{
+    db::Db,
+    schema::{login_attempts, reset_requests},
+    Error,
+};
+
+/// The number of failed logins allowed for an account before it is locked out.
+const ACCOUNT_THRESHOLD: i64 = 5;
+/// The number of failed logins allowed from an address before it is locked out, across all
+/// accounts.
+const ADDRESS_THRESHOLD: i64 = 20;
+/// The number of password reset e-mails which may be requested for an account, and from an
+/// address, before further requests are refused.
+const RESET_ACCOUNT_THRESHOLD: i64 = 3;
+const RESET_ADDRESS_THRESHOLD: i64 = 10;
+/// How long the first lockout lasts; every further failure doubles it.
+const BASE_DELAY: TimeDelta = TimeDelta::seconds(30);
+const MAX_DELAY: TimeDelta = TimeDelta::hours(1);
+/// How long failed logins count towards a lockout.
+const WINDOW: TimeDelta = TimeDelta::days(1);
+/// How long attempts are kept for auditing before they are removed.
+const RETENTION: TimeDelta = TimeDelta::days(90);
+
+/// A record of an attempt to log in, kept both for throttling guesses and for auditing.
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = login_attempts, check_for_backend(Pg))]
+pub struct LoginAttempt {
+    pub id: i64,
+    /// The account matching the login, if any.
+    pub user_id: Option<i64>,
+    /// The username or e-mail address as entered.
+    pub login: String,
+    /// The address of the client making the attempt.
+    pub address: String,
+    pub success: bool,
+    pub attempted_on: DateTime<Utc>,
+}
+
+impl LoginAttempt {
+    pub fn record<C>(
+        user_id: Option<i64>,
+        login: &str,
+        address: &str,
+        success: bool,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(0 != diesel::insert_into(login_attempts::table)
+            .values((
+                login_attempts::user_id.eq(user_id),
+                login_attempts::login.eq(login),
+                login_attempts::address.eq(address),
+                login_attempts::success.eq(success),
+                login_attempts::attempted_on.eq(now),
+            ))
+            .execute(conn)?)
+    }
+
+    /// Returns until when logins are refused for the account and the address, if they are
+    /// locked out at `now`.
+    ///
+    /// An account is locked out after repeated failures since its last successful login, so that
+    /// its owner is not locked out for good by someone guessing. An address is locked out after
+    /// repeated failures regardless of successes, so that guesses cannot be spread over accounts.
+    pub fn locked_until<C>(
+        user_id: Option<i64>,
+        address: &str,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<Option<DateTime<Utc>>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        let mut until = None;
+
+        if let Some(user_id) = user_id {
+            let last_success: Option<DateTime<Utc>> = login_attempts::table
+                .filter(login_attempts::user_id.eq(user_id))
+                .filter(login_attempts::success.eq(true))
+                .select(diesel::dsl::max(login_attempts::attempted_on))
+                .get_result(conn)?;
+            let since = last_success.map_or(now - WINDOW, |last| last.max(now - WINDOW));
+
+            let (failures, last): (i64, Option<DateTime<Utc>>) = login_attempts::table
+                .filter(login_attempts::user_id.eq(user_id))
+                .filter(login_attempts::success.eq(false))
+                .filter(login_attempts::attempted_on.gt(since))
+                .select((count_star(), diesel::dsl::max(login_attempts::attempted_on)))
+                .get_result(conn)?;
+            until = until.max(lockout_end(failures, last, ACCOUNT_THRESHOLD));
+        }
+
+        let (failures, last): (i64, Option<DateTime<Utc>>) = login_attempts::table
+            .filter(login_attempts::address.eq(address))
+            .filter(login_attempts::success.eq(false))
+            .filter(login_attempts::attempted_on.gt(now - WINDOW))
+            .select((count_star(), diesel::dsl::max(login_attempts::attempted_on)))
+            .get_result(conn)?;
+        until = until.max(lockout_end(failures, last, ADDRESS_THRESHOLD));
+
+        Ok(until.filter(|until| *until > now))
+    }
+}
+
+/// A request for a password reset e-mail, kept for throttling how many are sent, so that the
+/// form cannot be used to flood someone with e-mail.
+pub struct ResetRequest;
+
+impl ResetRequest {
+    pub fn record<C>(
+        user_id: Option<i64>,
+        address: &str,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(0 != diesel::insert_into(reset_requests::table)
+            .values((
+                reset_requests::user_id.eq(user_id),
+                reset_requests::address.eq(address),
+                reset_requests::requested_on.eq(now),
+            ))
+            .execute(conn)?)
+    }
+
+    /// Returns until when reset e-mails are refused for the account and the address, if they
+    /// are locked out at `now`, backing off like failed logins do.
+    pub fn locked_until<C>(
+        user_id: Option<i64>,
+        address: &str,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<Option<DateTime<Utc>>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        let mut until = None;
+
+        if let Some(user_id) = user_id {
+            let (requests, last): (i64, Option<DateTime<Utc>>) = reset_requests::table
+                .filter(reset_requests::user_id.eq(user_id))
+                .filter(reset_requests::requested_on.gt(now - WINDOW))
+                .select((count_star(), diesel::dsl::max(reset_requests::requested_on)))
+                .get_result(conn)?;
+            until = until.max(lockout_end(requests, last, RESET_ACCOUNT_THRESHOLD));
+        }
+
+        let (requests, last): (i64, Option<DateTime<Utc>>) = reset_requests::table
+            .filter(reset_requests::address.eq(address))
+            .filter(reset_requests::requested_on.gt(now - WINDOW))
+            .select((count_star(), diesel::dsl::max(reset_requests::requested_on)))
+            .get_result(conn)?;
+        until = until.max(lockout_end(requests, last, RESET_ADDRESS_THRESHOLD));
+
+        Ok(until.filter(|until| *until > now))
+    }
+}
+
+/// Removes login attempts and reset requests older than the retention period, which no longer
+/// count towards lockouts.
+pub fn cleanup_attempts(db: &Db, now: DateTime<Utc>) -> Result<bool, Error> {
+    let mut conn = db.pool.get()?;
+    let attempts = diesel::delete(
+        login_attempts::table.filter(login_attempts::attempted_on.lt(now - RETENTION)),
+    )
+    .execute(&mut conn)?;
+    let requests = diesel::delete(
+        reset_requests::table.filter(reset_requests::requested_on.lt(now - RETENTION)),
+    )
+    .execute(&mut conn)?;
+
+    Ok(0 != attempts + requests)
+}
+
+fn lockout_end(
+    failures: i64,
+    last: Option<DateTime<Utc>>,
+    threshold: i64,
+) -> Option<DateTime<Utc>> {
+    Some(last? + backoff(failures, threshold)?)
+}
+
+/// Returns how long to refuse logins after `failures` failed attempts, doubling with every
+/// failure past `threshold`.
+fn backoff(failures: i64, threshold: i64) -> Option<TimeDelta> {
+    if failures < threshold {
+        return None;
+    }
+
+    let doublings = (failures - threshold).min(16) as u32;
+    Some((BASE_DELAY * 2i32.pow(doublings)).min(MAX_DELAY))
+}
+
+#[test]
+fn backoff_test() {
+    assert_eq!(backoff(4, 5), None);
+    assert_eq!(backoff(5, 5), Some(TimeDelta::seconds(30)));
+    assert_eq!(backoff(6, 5), Some(TimeDelta::seconds(60)));
+    assert_eq!(backoff(8, 5), Some(TimeDelta::seconds(240)));
+    assert_eq!(backoff(12, 5), Some(TimeDelta::hours(1)));
+    assert_eq!(backoff(1000, 5), Some(TimeDelta::hours(1)));
+}
@@ -1,2 +1,11 @@
+pub mod archive;
+pub mod discussion;
+pub mod group;
+pub mod login;
+pub mod namespace;
 pub mod page;
+pub mod protection;
+pub mod report;
+pub mod search;
+pub mod totp;
 pub mod user;
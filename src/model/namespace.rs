@@ -0,0 +1,155 @@
+use diesel::{
+    backend::Backend,
+    deserialize::{FromSql, FromSqlRow},
+    expression::AsExpression,
+    query_builder::bind_collector::RawBytesBindCollector,
+    serialize::ToSql,
+    sql_types::SmallInt,
+};
+
+/// A namespace of pages, recognised by the prefix of a page title such as `Talk:`.
+///
+/// Page titles are stored without their namespace prefix, and the namespace is stored separately
+/// by its id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, AsExpression, FromSqlRow)]
+#[diesel(sql_type = SmallInt, check_for_backend(Pg))]
+pub enum Namespace {
+    #[default]
+    Main,
+    Talk,
+    User,
+    UserTalk,
+    Template,
+    TemplateTalk,
+    Help,
+    HelpTalk,
+    Category,
+    CategoryTalk,
+    /// Pages generated by the software itself, such as lists of pages; nothing is stored in it.
+    Special,
+}
+
+impl Namespace {
+    pub const ALL: [Self; 11] = [
+        Self::Main,
+        Self::Talk,
+        Self::User,
+        Self::UserTalk,
+        Self::Template,
+        Self::TemplateTalk,
+        Self::Help,
+        Self::HelpTalk,
+        Self::Category,
+        Self::CategoryTalk,
+        Self::Special,
+    ];
+
+    pub fn from_id(id: i16) -> Option<Self> {
+        Self::ALL.into_iter().find(|namespace| namespace.id() == id)
+    }
+
+    pub fn id(&self) -> i16 {
+        match self {
+            Self::Main => 0,
+            Self::Talk => 1,
+            Self::User => 2,
+            Self::UserTalk => 3,
+            Self::Template => 10,
+            Self::TemplateTalk => 11,
+            Self::Help => 12,
+            Self::HelpTalk => 13,
+            Self::Category => 14,
+            Self::CategoryTalk => 15,
+            Self::Special => -1,
+        }
+    }
+
+    /// Recognises a title prefix, without the trailing colon, case-insensitively.
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|namespace| {
+            namespace
+                .prefix()
+                .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+        })
+    }
+
+    /// The canonical title prefix, without the trailing colon; the main namespace has none.
+    pub fn prefix(&self) -> Option<&'static str> {
+        Some(match self {
+            Self::Main => return None,
+            Self::Talk => "Talk",
+            Self::User => "User",
+            Self::UserTalk => "User talk",
+            Self::Template => "Template",
+            Self::TemplateTalk => "Template talk",
+            Self::Help => "Help",
+            Self::HelpTalk => "Help talk",
+            Self::Category => "Category",
+            Self::CategoryTalk => "Category talk",
+            Self::Special => "Special",
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.prefix().unwrap_or("Main")
+    }
+
+    /// The namespace holding the talk pages of this namespace, if it has any.
+    pub fn talk(&self) -> Option<Self> {
+        match self {
+            Self::Main => Some(Self::Talk),
+            Self::User => Some(Self::UserTalk),
+            Self::Template => Some(Self::TemplateTalk),
+            Self::Help => Some(Self::HelpTalk),
+            Self::Category => Some(Self::CategoryTalk),
+            _ => None,
+        }
+    }
+
+    /// The namespace of the pages discussed by the talk pages of this namespace.
+    pub fn subject(&self) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|namespace| namespace.talk() == Some(*self))
+    }
+
+    pub fn is_talk(&self) -> bool {
+        self.subject().is_some()
+    }
+
+    /// Content namespaces hold the encyclopedic articles themselves, as opposed to discussions
+    /// and pages supporting the wiki.
+    pub fn is_content(&self) -> bool {
+        matches!(self, Self::Main)
+    }
+
+    /// Whether pages in this namespace are generated on request rather than stored and edited.
+    pub fn is_special(&self) -> bool {
+        matches!(self, Self::Special)
+    }
+}
+
+impl<DB> FromSql<SmallInt, DB> for Namespace
+where
+    DB: Backend,
+    i16: FromSql<SmallInt, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        let id = i16::from_sql(bytes)?;
+        Self::from_id(id).ok_or_else(|| format!("invalid namespace '{id}'").into())
+    }
+}
+
+impl<DB> ToSql<SmallInt, DB> for Namespace
+where
+    DB: Backend,
+    i16: ToSql<SmallInt, DB>,
+    for<'c> DB: Backend<BindCollector<'c> = RawBytesBindCollector<DB>>,
+{
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, DB>,
+    ) -> diesel::serialize::Result {
+        self.id().to_sql(&mut out.reborrow())
+    }
+}
@@ -1,15 +1,33 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use diesel::{
-    connection::LoadConnection, pg::Pg, prelude::Insertable, Connection, ExpressionMethods,
-    OptionalExtension, QueryDsl, Queryable, RunQueryDsl, Selectable,
+    connection::LoadConnection,
+    define_sql_function,
+    dsl::{count_star, sql},
+    pg::Pg,
+    prelude::Insertable,
+    sql_types::{Binary, Double, Integer, Text},
+    Connection, ExpressionMethods, JoinOnDsl, OptionalExtension, QueryDsl, Queryable, RunQueryDsl,
+    Selectable, TextExpressionMethods,
 };
 
 use crate::{
+    model::namespace::Namespace,
     output::Body,
-    schema::{contents, pages, revisions},
+    schema::{contents, page_categories, page_links, page_redirects, pages, revisions, users},
     Error,
 };
 
+diesel::allow_columns_to_appear_in_same_group_by_clause!(
+    page_categories::category,
+    pages::namespace
+);
+
+define_sql_function!(fn octet_length(bytes: Binary) -> Integer);
+define_sql_function!(fn lower(text: Text) -> Text);
+define_sql_function!(fn levenshtein_less_equal(a: Text, b: Text, max_distance: Integer) -> Integer);
+
 #[derive(Queryable, Selectable)]
 #[diesel(check_for_backend(Pg))]
 pub struct Page {
@@ -20,20 +38,192 @@ pub struct Page {
     pub root_id: i64,
 
     pub created_on: DateTime<Utc>,
+    pub namespace: Namespace,
 }
 
 impl Page {
-    pub fn by_title<C>(title: &str, conn: &mut C) -> Result<Option<Self>, Error>
+    pub fn by_title<C>(
+        namespace: Namespace,
+        title: &str,
+        conn: &mut C,
+    ) -> Result<Option<Self>, Error>
     where
         C: Connection<Backend = Pg> + LoadConnection,
     {
         Ok(pages::table
+            .filter(pages::namespace.eq(namespace))
             .filter(pages::title.eq(title))
             .select(pages::all_columns)
             .get_result(conn)
             .optional()?)
     }
 
+    /// Returns those of the given titles which belong to an existing page, in any namespace.
+    pub fn existing_titles<C>(
+        titles: &[&str],
+        conn: &mut C,
+    ) -> Result<Vec<(Namespace, String)>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(pages::table
+            .filter(pages::title.eq_any(titles))
+            .select((pages::namespace, pages::title))
+            .load(conn)?)
+    }
+
+    /// Lists the titles of all subpages of a page, i.e. those below `title/` in its namespace.
+    pub fn subpage_titles<C>(
+        namespace: Namespace,
+        title: &str,
+        conn: &mut C,
+    ) -> Result<Vec<String>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        // backslash is the default escape character of `like` in postgres
+        let pattern = format!(
+            "{}/%",
+            title
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        Ok(pages::table
+            .filter(pages::namespace.eq(namespace))
+            .filter(pages::title.like(pattern))
+            .select(pages::title)
+            .order_by(pages::title)
+            .load(conn)?)
+    }
+
+    /// Lists the titles in a namespace which start with `prefix`, ignoring case, in alphabetical
+    /// order.
+    pub fn suggest<C>(
+        namespace: Namespace,
+        prefix: &str,
+        limit: i64,
+        conn: &mut C,
+    ) -> Result<Vec<String>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        // backslash is the default escape character of `like` in postgres
+        let pattern = format!(
+            "{}%",
+            prefix
+                .to_lowercase()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        Ok(pages::table
+            .filter(pages::namespace.eq(namespace))
+            .filter(lower(pages::title).like(pattern))
+            .select(pages::title)
+            .order_by(pages::title)
+            .limit(limit)
+            .load(conn)?)
+    }
+
+    /// Lists the titles in a namespace which differ from `title` by at most `max_distance` edits,
+    /// ignoring case, closest first.
+    pub fn similar<C>(
+        namespace: Namespace,
+        title: &str,
+        max_distance: i32,
+        limit: i64,
+        conn: &mut C,
+    ) -> Result<Vec<String>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        let title_lower = title.to_lowercase();
+        let distance =
+            || levenshtein_less_equal(lower(pages::title), title_lower.clone(), max_distance);
+
+        Ok(pages::table
+            .filter(pages::namespace.eq(namespace))
+            .filter(pages::title.ne(title))
+            .filter(distance().le(max_distance))
+            .select(pages::title)
+            .order_by((distance(), pages::title))
+            .limit(limit)
+            .load(conn)?)
+    }
+
+    /// Lists the titles of the pages in a namespace in alphabetical order.
+    pub fn titles<C>(
+        namespace: Namespace,
+        offset: i64,
+        limit: i64,
+        conn: &mut C,
+    ) -> Result<Vec<String>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(pages::table
+            .filter(pages::namespace.eq(namespace))
+            .select(pages::title)
+            .order_by(pages::title)
+            .offset(offset)
+            .limit(limit)
+            .load(conn)?)
+    }
+
+    /// Picks a page from a namespace at random, skipping redirects.
+    pub fn random<C>(namespace: Namespace, conn: &mut C) -> Result<Option<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(pages::table
+            .left_join(page_redirects::table)
+            .filter(pages::namespace.eq(namespace))
+            .filter(page_redirects::page_id.is_null())
+            .select(pages::all_columns)
+            .order_by(sql::<Double>("random()"))
+            .first(conn)
+            .optional()?)
+    }
+
+    /// Lists pages other than redirects by the size of their current content, largest first.
+    pub fn longest<C>(
+        offset: i64,
+        limit: i64,
+        conn: &mut C,
+    ) -> Result<Vec<(Namespace, String, i32)>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(pages::table
+            .left_join(page_redirects::table)
+            .inner_join(revisions::table.on(revisions::id.eq(pages::rev_id)))
+            .inner_join(contents::table.on(contents::id.eq(revisions::content_id)))
+            .filter(page_redirects::page_id.is_null())
+            .select((pages::namespace, pages::title, octet_length(contents::body)))
+            .order_by((octet_length(contents::body).desc(), pages::title))
+            .offset(offset)
+            .limit(limit)
+            .load(conn)?)
+    }
+
+    /// Lists the most recently created pages along with the name of their creator, newest first.
+    pub fn newest<C>(offset: i64, limit: i64, conn: &mut C) -> Result<Vec<(Self, String)>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(pages::table
+            .inner_join(revisions::table.on(revisions::id.eq(pages::root_id)))
+            .inner_join(users::table.on(users::id.eq(revisions::user_id)))
+            .select((pages::all_columns, users::name))
+            .order_by((pages::created_on.desc(), pages::id.desc()))
+            .offset(offset)
+            .limit(limit)
+            .load(conn)?)
+    }
+
     pub fn set_revision<C>(&mut self, revision: &Revision, conn: &mut C) -> Result<bool, Error>
     where
         C: Connection<Backend = Pg>,
@@ -50,6 +240,7 @@ impl Page {
 #[derive(Insertable)]
 #[diesel(table_name = pages, check_for_backend(Pg))]
 pub struct NewPage<'a> {
+    pub namespace: Namespace,
     pub title: &'a str,
     pub rev_id: i64,
     pub root_id: i64,
@@ -58,13 +249,19 @@ pub struct NewPage<'a> {
 }
 
 impl<'a> NewPage<'a> {
-    pub fn new(title: &'a str, rev_id: i64, created_on: Option<DateTime<Utc>>) -> Self {
+    pub fn new(
+        namespace: Namespace,
+        title: &'a str,
+        rev_id: i64,
+        created_on: Option<DateTime<Utc>>,
+    ) -> Self {
         Self {
+            namespace,
             title,
             rev_id,
             root_id: rev_id,
 
-            created_on: created_on.unwrap_or_else(|| Utc::now()),
+            created_on: created_on.unwrap_or_else(Utc::now),
         }
     }
 
@@ -73,6 +270,7 @@ impl<'a> NewPage<'a> {
         C: Connection<Backend = Pg> + LoadConnection,
     {
         Ok(0 != pages::table
+            .filter(pages::namespace.eq(self.namespace))
             .filter(pages::title.eq(self.title))
             .count()
             .get_result::<i64>(conn)?)
@@ -112,6 +310,25 @@ impl Revision {
             .get_result(conn)
             .optional()?)
     }
+
+    /// Loads the revision with the given id along with all of its ancestors, newest first.
+    pub fn chain<C>(id: i64, conn: &mut C) -> Result<Vec<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        let mut chain = Vec::new();
+        let mut next = Some(id);
+        while let Some(id) = next {
+            let Some(revision) = Self::by_id(id, conn)? else {
+                break;
+            };
+
+            next = revision.parent_id;
+            chain.push(revision);
+        }
+
+        Ok(chain)
+    }
 }
 
 #[derive(Insertable)]
@@ -136,7 +353,7 @@ impl NewRevision {
             content_id,
 
             user_id,
-            created_on: created_on.unwrap_or_else(|| Utc::now()),
+            created_on: created_on.unwrap_or_else(Utc::now),
         }
     }
 
@@ -194,3 +411,228 @@ impl NewContent {
             .get_result(conn)?)
     }
 }
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = page_redirects, check_for_backend(Pg))]
+pub struct PageRedirect {
+    pub page_id: i64,
+    pub target: String,
+    pub target_namespace: Namespace,
+}
+
+impl PageRedirect {
+    pub fn by_page_id<C>(page_id: i64, conn: &mut C) -> Result<Option<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(page_redirects::table
+            .filter(page_redirects::page_id.eq(page_id))
+            .select(page_redirects::all_columns)
+            .get_result(conn)
+            .optional()?)
+    }
+
+    /// Records `target` as the redirect target of the page, or removes the record if `None`.
+    pub fn set<C>(
+        page_id: i64,
+        target: Option<(Namespace, &str)>,
+        conn: &mut C,
+    ) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(0 != match target {
+            Some((target_namespace, target)) => diesel::insert_into(page_redirects::table)
+                .values((
+                    page_redirects::page_id.eq(page_id),
+                    page_redirects::target.eq(target),
+                    page_redirects::target_namespace.eq(target_namespace),
+                ))
+                .on_conflict(page_redirects::page_id)
+                .do_update()
+                .set((
+                    page_redirects::target.eq(target),
+                    page_redirects::target_namespace.eq(target_namespace),
+                ))
+                .execute(conn)?,
+            None => {
+                diesel::delete(page_redirects::table.filter(page_redirects::page_id.eq(page_id)))
+                    .execute(conn)?
+            }
+        })
+    }
+
+    /// Lists all redirects as pairs of source and target titles, ordered by source title.
+    #[allow(clippy::type_complexity)]
+    pub fn list<C>(conn: &mut C) -> Result<Vec<((Namespace, String), (Namespace, String))>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(page_redirects::table
+            .inner_join(pages::table)
+            .select((
+                (pages::namespace, pages::title),
+                (page_redirects::target_namespace, page_redirects::target),
+            ))
+            .order_by((pages::namespace, pages::title))
+            .load(conn)?)
+    }
+}
+
+/// Membership of a page in a category, as declared by a `[[Category:Name]]` link in its content.
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = page_categories, check_for_backend(Pg))]
+pub struct PageCategory {
+    pub page_id: i64,
+    /// The name of the category page, as stored without the `Category:` prefix.
+    pub category: String,
+}
+
+impl PageCategory {
+    /// Lists the categories of a page, ordered by name.
+    pub fn by_page_id<C>(page_id: i64, conn: &mut C) -> Result<Vec<String>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(page_categories::table
+            .filter(page_categories::page_id.eq(page_id))
+            .select(page_categories::category)
+            .order_by(page_categories::category)
+            .load(conn)?)
+    }
+
+    /// Replaces the categories of a page with `categories`.
+    pub fn set<C>(page_id: i64, categories: &[&str], conn: &mut C) -> Result<(), Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        conn.transaction(|conn| {
+            diesel::delete(page_categories::table.filter(page_categories::page_id.eq(page_id)))
+                .execute(conn)?;
+            diesel::insert_into(page_categories::table)
+                .values(
+                    categories
+                        .iter()
+                        .map(|category| {
+                            (
+                                page_categories::page_id.eq(page_id),
+                                page_categories::category.eq(*category),
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    /// Lists a page of the members of a category, ordered by title.
+    ///
+    /// Subcategories are listed separately from other pages, depending on `subcategories`.
+    pub fn members<C>(
+        category: &str,
+        subcategories: bool,
+        offset: i64,
+        limit: i64,
+        conn: &mut C,
+    ) -> Result<Vec<(Namespace, String)>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        let query = page_categories::table
+            .inner_join(pages::table)
+            .filter(page_categories::category.eq(category))
+            .select((pages::namespace, pages::title))
+            .order_by((pages::title, pages::namespace))
+            .offset(offset)
+            .limit(limit);
+
+        Ok(if subcategories {
+            query
+                .filter(pages::namespace.eq(Namespace::Category))
+                .load(conn)?
+        } else {
+            query
+                .filter(pages::namespace.ne(Namespace::Category))
+                .load(conn)?
+        })
+    }
+
+    /// Counts the members of each of `categories`, with a single grouped query.
+    ///
+    /// Categories without members are missing from the result.
+    pub fn count_members<C>(
+        categories: &[&str],
+        conn: &mut C,
+    ) -> Result<HashMap<String, MemberCounts>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        let rows: Vec<(String, Namespace, i64)> = page_categories::table
+            .inner_join(pages::table)
+            .filter(page_categories::category.eq_any(categories))
+            .group_by((page_categories::category, pages::namespace))
+            .select((page_categories::category, pages::namespace, count_star()))
+            .load(conn)?;
+
+        let mut counts = HashMap::<_, MemberCounts>::new();
+        for (category, namespace, count) in rows {
+            let entry = counts.entry(category).or_default();
+            if namespace == Namespace::Category {
+                entry.subcategories += count;
+            } else {
+                entry.pages += count;
+            }
+        }
+
+        Ok(counts)
+    }
+}
+
+/// The number of subcategories and of other pages in a category.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemberCounts {
+    pub subcategories: i64,
+    pub pages: i64,
+}
+
+/// A link from a page to another title, which need not exist, as found in the page content.
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = page_links, check_for_backend(Pg))]
+pub struct PageLink {
+    pub page_id: i64,
+    pub target_namespace: Namespace,
+    pub target: String,
+}
+
+impl PageLink {
+    /// Replaces the outgoing links of a page with links to `targets`.
+    pub fn set<C>(page_id: i64, targets: &[(Namespace, &str)], conn: &mut C) -> Result<(), Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        conn.transaction(|conn| {
+            diesel::delete(page_links::table.filter(page_links::page_id.eq(page_id)))
+                .execute(conn)?;
+            diesel::insert_into(page_links::table)
+                .values(
+                    targets
+                        .iter()
+                        .map(|(target_namespace, target)| {
+                            (
+                                page_links::page_id.eq(page_id),
+                                page_links::target_namespace.eq(*target_namespace),
+                                page_links::target.eq(*target),
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+}
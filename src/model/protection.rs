@@ -0,0 +1,260 @@
+use chrono::{DateTime, Utc};
+use diesel::{
+    backend::Backend,
+    connection::LoadConnection,
+    deserialize::{FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::Pg,
+    serialize::ToSql,
+    sql_types::SmallInt,
+    BoolExpressionMethods, Connection, ExpressionMethods, QueryDsl, Queryable, RunQueryDsl,
+    Selectable,
+};
+use serde::Deserialize;
+
+use crate::{
+    auth::{Permission, Permissions},
+    schema::page_protections,
+    Error,
+};
+
+/// A restriction on who may perform `action` on a page, optionally until it expires.
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = page_protections, check_for_backend(Pg))]
+pub struct Protection {
+    pub page_id: i64,
+    pub action: ProtectedAction,
+    pub level: ProtectionLevel,
+    pub expire_on: Option<DateTime<Utc>>,
+    pub reason: String,
+
+    pub user_id: i64,
+    pub created_on: DateTime<Utc>,
+}
+
+impl Protection {
+    /// Lists the protections of a page which have not yet expired.
+    pub fn active_by_page_id<C>(
+        page_id: i64,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<Vec<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(page_protections::table
+            .filter(page_protections::page_id.eq(page_id))
+            .filter(
+                page_protections::expire_on
+                    .is_null()
+                    .or(page_protections::expire_on.gt(now)),
+            )
+            .order_by(page_protections::action)
+            .select(page_protections::all_columns)
+            .load(conn)?)
+    }
+
+    /// Returns the level required to perform `action` on a page right now.
+    pub fn required_level<C>(
+        page_id: i64,
+        action: ProtectedAction,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<ProtectionLevel, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(Self::active_by_page_id(page_id, now, conn)?
+            .into_iter()
+            .find(|protection| protection.action == action)
+            .map_or(ProtectionLevel::None, |protection| protection.level))
+    }
+
+    /// Protects `action` on a page at the given level, replacing any previous protection.
+    ///
+    /// Setting the level to `ProtectionLevel::None` removes the protection altogether.
+    pub fn set<C>(
+        page_id: i64,
+        action: ProtectedAction,
+        level: ProtectionLevel,
+        expire_on: Option<DateTime<Utc>>,
+        reason: &str,
+        user_id: i64,
+        conn: &mut C,
+    ) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        let existing = page_protections::table
+            .filter(page_protections::page_id.eq(page_id))
+            .filter(page_protections::action.eq(action));
+
+        Ok(0 != if level == ProtectionLevel::None {
+            diesel::delete(existing).execute(conn)?
+        } else {
+            diesel::insert_into(page_protections::table)
+                .values((
+                    page_protections::page_id.eq(page_id),
+                    page_protections::action.eq(action),
+                    page_protections::level.eq(level),
+                    page_protections::expire_on.eq(expire_on),
+                    page_protections::reason.eq(reason),
+                    page_protections::user_id.eq(user_id),
+                    page_protections::created_on.eq(Utc::now()),
+                ))
+                .on_conflict((page_protections::page_id, page_protections::action))
+                .do_update()
+                .set((
+                    page_protections::level.eq(level),
+                    page_protections::expire_on.eq(expire_on),
+                    page_protections::reason.eq(reason),
+                    page_protections::user_id.eq(user_id),
+                    page_protections::created_on.eq(Utc::now()),
+                ))
+                .execute(conn)?
+        })
+    }
+}
+
+/// An action on a page which can be protected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = SmallInt, check_for_backend(Pg))]
+pub enum ProtectedAction {
+    Edit,
+    Move,
+}
+
+impl ProtectedAction {
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            Self::Edit => "edit",
+            Self::Move => "move",
+        }
+    }
+}
+
+impl<DB> FromSql<SmallInt, DB> for ProtectedAction
+where
+    DB: Backend,
+    i16: FromSql<SmallInt, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        Ok(match i16::from_sql(bytes)? {
+            0 => Self::Edit,
+            1 => Self::Move,
+            action => return Err(format!("invalid protected action '{action}'").into()),
+        })
+    }
+}
+
+impl<DB> ToSql<SmallInt, DB> for ProtectedAction
+where
+    DB: Backend,
+    i16: ToSql<SmallInt, DB>,
+{
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, DB>,
+    ) -> diesel::serialize::Result {
+        match self {
+            Self::Edit => 0_i16.to_sql(out),
+            Self::Move => 1_i16.to_sql(out),
+        }
+    }
+}
+
+/// Who may perform a protected action, from least to most restrictive.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+)]
+#[serde(rename_all = "kebab-case")]
+#[diesel(sql_type = SmallInt, check_for_backend(Pg))]
+pub enum ProtectionLevel {
+    #[default]
+    None,
+    LoggedIn,
+    Trusted,
+    Admin,
+}
+
+impl ProtectionLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::LoggedIn => "logged-in",
+            Self::Trusted => "trusted",
+            Self::Admin => "admin",
+        }
+    }
+
+    /// Checks whether a user or anonymous visitor with the given permissions is permitted.
+    pub fn permits(&self, permissions: &Permissions) -> bool {
+        match self {
+            Self::None => true,
+            Self::LoggedIn => permissions.logged_in,
+            Self::Trusted => permissions.has(Permission::Trusted),
+            Self::Admin => permissions.has(Permission::Protect),
+        }
+    }
+}
+
+impl<DB> FromSql<SmallInt, DB> for ProtectionLevel
+where
+    DB: Backend,
+    i16: FromSql<SmallInt, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        Ok(match i16::from_sql(bytes)? {
+            0 => Self::None,
+            1 => Self::LoggedIn,
+            2 => Self::Trusted,
+            3 => Self::Admin,
+            level => return Err(format!("invalid protection level '{level}'").into()),
+        })
+    }
+}
+
+impl<DB> ToSql<SmallInt, DB> for ProtectionLevel
+where
+    DB: Backend,
+    i16: ToSql<SmallInt, DB>,
+{
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, DB>,
+    ) -> diesel::serialize::Result {
+        match self {
+            Self::None => 0_i16.to_sql(out),
+            Self::LoggedIn => 1_i16.to_sql(out),
+            Self::Trusted => 2_i16.to_sql(out),
+            Self::Admin => 3_i16.to_sql(out),
+        }
+    }
+}
+
+#[test]
+fn protection_level_test() {
+    let config = crate::auth::PermissionConfig::default();
+    let anonymous = config.resolve(None::<[&str; 0]>);
+    let user = config.resolve(Some([]));
+    let reviewer = config.resolve(Some(["reviewer"]));
+    let admin = config.resolve(Some(["admin"]));
+
+    assert!(ProtectionLevel::None.permits(&anonymous));
+    assert!(!ProtectionLevel::LoggedIn.permits(&anonymous));
+    assert!(ProtectionLevel::LoggedIn.permits(&user));
+    assert!(!ProtectionLevel::Trusted.permits(&user));
+    assert!(ProtectionLevel::Trusted.permits(&reviewer));
+    assert!(ProtectionLevel::Trusted.permits(&admin));
+    assert!(!ProtectionLevel::Admin.permits(&reviewer));
+}
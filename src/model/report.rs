@@ -0,0 +1,182 @@
+use chrono::{DateTime, Utc};
+use diesel::{
+    backend::Backend,
+    connection::LoadConnection,
+    deserialize::{FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::Pg,
+    serialize::ToSql,
+    sql_types::{SmallInt, Timestamptz},
+    Connection, ExpressionMethods, QueryDsl, Queryable, RunQueryDsl, Selectable,
+};
+
+use crate::{db::Db, model::namespace::Namespace, schema::cached_reports, Error};
+
+/// A maintenance report computed from the link graph, as cached in `cached_reports`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow)]
+#[diesel(sql_type = SmallInt, check_for_backend(Pg))]
+pub enum Report {
+    /// Articles which no other page links to.
+    Orphaned,
+    /// Articles which do not link to any other page.
+    DeadEnd,
+    /// Titles which do not exist but are linked to, along with the number of links.
+    Wanted,
+}
+
+impl Report {
+    pub const ALL: [Self; 3] = [Self::Orphaned, Self::DeadEnd, Self::Wanted];
+
+    pub fn as_text(&self) -> &'static str {
+        match self {
+            Self::Orphaned => "orphaned",
+            Self::DeadEnd => "dead-end",
+            Self::Wanted => "wanted",
+        }
+    }
+
+    /// The query selecting the entries of the report as `(namespace, title, value)` rows.
+    fn source(&self) -> &'static str {
+        match self {
+            Self::Orphaned => {
+                "select p.namespace, p.title, 0
+                from pages p
+                where p.namespace = 0
+                    and not exists (select 1 from page_redirects r where r.page_id = p.id)
+                    and not exists (
+                        select 1 from page_links l
+                        where l.target_namespace = p.namespace
+                            and l.target = p.title
+                            and l.page_id <> p.id
+                    )"
+            }
+            Self::DeadEnd => {
+                "select p.namespace, p.title, 0
+                from pages p
+                where p.namespace = 0
+                    and not exists (select 1 from page_redirects r where r.page_id = p.id)
+                    and not exists (select 1 from page_links l where l.page_id = p.id)"
+            }
+            Self::Wanted => {
+                "select l.target_namespace, l.target, count(*)
+                from page_links l
+                where not exists (
+                    select 1 from pages p
+                    where p.namespace = l.target_namespace and p.title = l.target
+                )
+                group by l.target_namespace, l.target"
+            }
+        }
+    }
+}
+
+/// An entry of a cached maintenance report.
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = cached_reports, check_for_backend(Pg))]
+pub struct CachedReport {
+    pub report: Report,
+    pub namespace: Namespace,
+    pub title: String,
+    /// The number of links for wanted pages; unused by other reports.
+    pub value: i64,
+
+    pub computed_on: DateTime<Utc>,
+}
+
+impl CachedReport {
+    /// Lists a page of the entries of a report, highest value first and then by title.
+    pub fn list<C>(
+        report: Report,
+        offset: i64,
+        limit: i64,
+        conn: &mut C,
+    ) -> Result<Vec<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(cached_reports::table
+            .filter(cached_reports::report.eq(report))
+            .select(cached_reports::all_columns)
+            .order_by((
+                cached_reports::value.desc(),
+                cached_reports::namespace,
+                cached_reports::title,
+            ))
+            .offset(offset)
+            .limit(limit)
+            .load(conn)?)
+    }
+
+    /// Returns when a report was last computed, if it has any entries.
+    pub fn computed_on<C>(report: Report, conn: &mut C) -> Result<Option<DateTime<Utc>>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(cached_reports::table
+            .filter(cached_reports::report.eq(report))
+            .select(diesel::dsl::max(cached_reports::computed_on))
+            .get_result(conn)?)
+    }
+
+    /// Recomputes a report from the link graph, replacing its cached entries.
+    pub fn refresh<C>(report: Report, now: DateTime<Utc>, conn: &mut C) -> Result<usize, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        conn.transaction(|conn| {
+            diesel::delete(cached_reports::table.filter(cached_reports::report.eq(report)))
+                .execute(conn)?;
+
+            Ok(diesel::sql_query(format!(
+                "insert into cached_reports (report, namespace, title, value, computed_on)
+                select $1, source.*, $2 from ({}) as source",
+                report.source()
+            ))
+            .bind::<SmallInt, _>(report)
+            .bind::<Timestamptz, _>(now)
+            .execute(conn)?)
+        })
+    }
+}
+
+/// Recomputes all maintenance reports.
+pub fn refresh_reports(db: &Db, now: DateTime<Utc>) -> Result<(), Error> {
+    let mut conn = db.pool.get()?;
+    for report in Report::ALL {
+        CachedReport::refresh(report, now, &mut conn)?;
+    }
+
+    Ok(())
+}
+
+impl<DB> FromSql<SmallInt, DB> for Report
+where
+    DB: Backend,
+    i16: FromSql<SmallInt, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> diesel::deserialize::Result<Self> {
+        match i16::from_sql(bytes)? {
+            0 => Ok(Self::Orphaned),
+            1 => Ok(Self::DeadEnd),
+            2 => Ok(Self::Wanted),
+            x => Err(format!("invalid report '{x}'").into()),
+        }
+    }
+}
+
+impl<DB> ToSql<SmallInt, DB> for Report
+where
+    DB: Backend,
+    i16: ToSql<SmallInt, DB>,
+{
+    fn to_sql<'b>(
+        &'b self,
+        out: &mut diesel::serialize::Output<'b, '_, DB>,
+    ) -> diesel::serialize::Result {
+        match self {
+            Self::Orphaned => 0_i16.to_sql(out),
+            Self::DeadEnd => 1_i16.to_sql(out),
+            Self::Wanted => 2_i16.to_sql(out),
+        }
+    }
+}
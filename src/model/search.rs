@@ -0,0 +1,224 @@
+use diesel::{
+    connection::LoadConnection,
+    pg::Pg,
+    sql_types::{Array, BigInt, Binary, Bool, Float, SmallInt, Text},
+    Connection, ExpressionMethods, QueryDsl, QueryableByName, RunQueryDsl,
+};
+
+use crate::{
+    db::Db,
+    formula,
+    model::namespace::Namespace,
+    output::Body,
+    schema::{page_formulas, page_search},
+    Error,
+};
+
+/// Marks the start of a matched term in search snippets.
+///
+/// Snippets are plain text, so matches are marked with private use characters which cannot be
+/// confused with page content, and are turned into markup only after the snippet is escaped.
+pub const MATCH_START: char = '\u{e000}';
+/// Marks the end of a matched term in search snippets.
+pub const MATCH_END: char = '\u{e001}';
+
+/// The text of a page as indexed for full-text search, kept in sync with its current revision.
+pub struct PageSearch;
+
+impl PageSearch {
+    /// Indexes the title and body text of a page, or removes it from the index if `None`.
+    pub fn set<C>(page_id: i64, text: Option<(&str, &str)>, conn: &mut C) -> Result<(), Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        match text {
+            Some((title, body)) => diesel::insert_into(page_search::table)
+                .values((
+                    page_search::page_id.eq(page_id),
+                    page_search::title.eq(title),
+                    page_search::body.eq(body),
+                ))
+                .on_conflict(page_search::page_id)
+                .do_update()
+                .set((page_search::title.eq(title), page_search::body.eq(body)))
+                .execute(conn)?,
+            None => diesel::delete(page_search::table)
+                .filter(page_search::page_id.eq(page_id))
+                .execute(conn)?,
+        };
+
+        Ok(())
+    }
+
+    /// Searches the index, returning a page of results ordered by relevance.
+    ///
+    /// The query uses web search syntax, i.e. quoted phrases, `or` and `-` for exclusion.
+    pub fn search<C>(
+        query: &str,
+        offset: i64,
+        limit: i64,
+        conn: &mut C,
+    ) -> Result<Vec<SearchResult>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(diesel::sql_query(format!(
+            "select pages.namespace, pages.title, ts_rank(page_search.vector, query) as rank,
+                ts_headline('english', page_search.body, query,
+                    'StartSel={MATCH_START}, StopSel={MATCH_END}, MaxFragments=2, MaxWords=30, MinWords=10')
+                    as snippet
+            from page_search
+                join pages on pages.id = page_search.page_id,
+                websearch_to_tsquery('english', $1) as query
+            where page_search.vector @@ query
+            order by rank desc, pages.title
+            offset $2 limit $3"
+        ))
+        .bind::<Text, _>(query)
+        .bind::<BigInt, _>(offset)
+        .bind::<BigInt, _>(limit)
+        .load(conn)?)
+    }
+}
+
+/// A page matching a full-text search query.
+#[derive(QueryableByName)]
+#[diesel(check_for_backend(Pg))]
+pub struct SearchResult {
+    #[diesel(sql_type = SmallInt)]
+    pub namespace: Namespace,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    #[diesel(sql_type = Float)]
+    pub rank: f32,
+    /// Fragments of the page body around the matches, with matched terms surrounded by
+    /// `MATCH_START` and `MATCH_END`.
+    #[diesel(sql_type = Text)]
+    pub snippet: String,
+}
+
+/// The formulas in the current content of a page, as indexed for formula search.
+pub struct PageFormula;
+
+impl PageFormula {
+    /// Replaces the indexed formulas of a page with `formulas`, given as TeX source.
+    pub fn set<C>(page_id: i64, formulas: &[&str], conn: &mut C) -> Result<(), Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        conn.transaction(|conn| {
+            diesel::delete(page_formulas::table.filter(page_formulas::page_id.eq(page_id)))
+                .execute(conn)?;
+            diesel::insert_into(page_formulas::table)
+                .values(
+                    formulas
+                        .iter()
+                        .filter_map(|source| {
+                            let tokens = formula::normalize(source);
+                            (!tokens.is_empty()).then(|| {
+                                (
+                                    page_formulas::page_id.eq(page_id),
+                                    page_formulas::source.eq(*source),
+                                    page_formulas::ngrams.eq(formula::ngrams(&tokens)),
+                                    page_formulas::tokens.eq(tokens.join(" ")),
+                                )
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    /// Searches for pages containing formulas structurally similar to the TeX formula `query`,
+    /// returning a page of results ordered by similarity.
+    ///
+    /// Formulas are compared by the overlap of their n-grams, with formulas of exactly the same
+    /// structure ranked first.
+    pub fn search<C>(
+        query: &str,
+        offset: i64,
+        limit: i64,
+        conn: &mut C,
+    ) -> Result<Vec<FormulaResult>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        let tokens = formula::normalize(query);
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(diesel::sql_query(
+            "select pages.namespace, pages.title, best.source, best.exact, best.similarity
+            from (
+                select distinct on (f.page_id) f.page_id, f.source,
+                    f.tokens = $2 as exact,
+                    (cardinality(array(select unnest(f.ngrams) intersect select unnest($1)))::real
+                        / cardinality(array(select unnest(f.ngrams) union select unnest($1))))::real
+                        as similarity
+                from page_formulas f
+                where f.ngrams && $1
+                order by f.page_id, exact desc, similarity desc
+            ) as best
+                join pages on pages.id = best.page_id
+            order by best.exact desc, best.similarity desc, pages.title
+            offset $3 limit $4",
+        )
+        .bind::<Array<Text>, _>(formula::ngrams(&tokens))
+        .bind::<Text, _>(tokens.join(" "))
+        .bind::<BigInt, _>(offset)
+        .bind::<BigInt, _>(limit)
+        .load(conn)?)
+    }
+}
+
+/// A page containing a formula similar to a formula search query.
+#[derive(QueryableByName)]
+#[diesel(check_for_backend(Pg))]
+pub struct FormulaResult {
+    #[diesel(sql_type = SmallInt)]
+    pub namespace: Namespace,
+    #[diesel(sql_type = Text)]
+    pub title: String,
+    /// The TeX source of the most similar formula on the page.
+    #[diesel(sql_type = Text)]
+    pub source: String,
+    /// Whether the formula has exactly the same structure as the query.
+    #[diesel(sql_type = Bool)]
+    pub exact: bool,
+    /// The share of n-grams in common between the formula and the query, from 0 to 1.
+    #[diesel(sql_type = Float)]
+    pub similarity: f32,
+}
+
+#[derive(QueryableByName)]
+struct UnindexedPage {
+    #[diesel(sql_type = BigInt)]
+    id: i64,
+    #[diesel(sql_type = Binary)]
+    body: Body,
+}
+
+/// Indexes the formulas of pages which may contain formulas but have none indexed, such as pages
+/// saved before formula search existed.
+pub fn index_formulas(db: &Db) -> Result<(), Error> {
+    let mut conn = db.pool.get()?;
+    let pages: Vec<UnindexedPage> = diesel::sql_query(
+        "select pages.id, contents.body
+        from pages
+            join revisions on revisions.id = pages.rev_id
+            join contents on contents.id = revisions.content_id
+        where position('$'::bytea in contents.body) > 0
+            and not exists (select 1 from page_formulas f where f.page_id = pages.id)",
+    )
+    .load(&mut conn)?;
+
+    for page in pages {
+        PageFormula::set(page.id, &page.body.formulas(), &mut conn)?;
+    }
+
+    Ok(())
+}
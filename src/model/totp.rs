@@ -0,0 +1,231 @@
+use chrono::{DateTime, Utc};
+use diesel::{
+    connection::LoadConnection, pg::Pg, Connection, ExpressionMethods, Insertable,
+    OptionalExtension, QueryDsl, Queryable, RunQueryDsl, Selectable,
+};
+
+use crate::{
+    auth::{
+        totp::{self, Totp},
+        Password, PasswordPolicy,
+    },
+    schema::{user_recovery_codes, user_totp},
+    Error,
+};
+
+/// The TOTP secret of a user, who has to enter a code from it when logging in once it is
+/// enabled.
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = user_totp, check_for_backend(Pg))]
+pub struct UserTotp {
+    pub user_id: i64,
+    pub secret: Vec<u8>,
+    /// When the user confirmed the secret with a code, or `None` while it is being enrolled.
+    pub enabled_on: Option<DateTime<Utc>>,
+    /// The last time step a code was accepted for.
+    pub last_step: i64,
+}
+
+impl UserTotp {
+    pub fn by_user_id<C>(user_id: i64, conn: &mut C) -> Result<Option<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(user_totp::table
+            .filter(user_totp::user_id.eq(user_id))
+            .select(user_totp::all_columns)
+            .get_result(conn)
+            .optional()?)
+    }
+
+    /// Returns whether logins of `user_id` require a code.
+    pub fn is_enabled_for<C>(user_id: i64, conn: &mut C) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(Self::by_user_id(user_id, conn)?.is_some_and(|totp| totp.is_enabled()))
+    }
+
+    /// Starts enrolling a new random secret, replacing any earlier one that was not enabled.
+    pub fn enroll<C>(user_id: i64, conn: &mut C) -> Result<Self, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        let totp = Self {
+            user_id,
+            secret: Totp::generate().secret,
+            enabled_on: None,
+            last_step: 0,
+        };
+        _ = diesel::insert_into(user_totp::table)
+            .values(&totp)
+            .on_conflict(user_totp::user_id)
+            .do_update()
+            .set((
+                user_totp::secret.eq(&totp.secret),
+                user_totp::enabled_on.eq(None::<DateTime<Utc>>),
+                user_totp::last_step.eq(0),
+            ))
+            .execute(conn)?;
+
+        Ok(totp)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_on.is_some()
+    }
+
+    pub fn totp(&self) -> Totp {
+        Totp::new(self.secret.clone())
+    }
+
+    /// Checks a code entered at `now`, and marks its time step as used if it is valid.
+    ///
+    /// Returns whether the code was accepted. A code is only ever accepted once, even when two
+    /// requests race to use it.
+    pub fn verify<C>(&self, code: &str, now: DateTime<Utc>, conn: &mut C) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        let Some(step) = self.totp().verify(code, now.timestamp(), self.last_step) else {
+            return Ok(false);
+        };
+
+        Ok(0 != diesel::update(
+            user_totp::table
+                .filter(user_totp::user_id.eq(self.user_id))
+                .filter(user_totp::last_step.lt(step)),
+        )
+        .set(user_totp::last_step.eq(step))
+        .execute(conn)?)
+    }
+
+    pub fn enable<C>(&self, now: DateTime<Utc>, conn: &mut C) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(
+            0 != diesel::update(user_totp::table.filter(user_totp::user_id.eq(self.user_id)))
+                .set(user_totp::enabled_on.eq(now))
+                .execute(conn)?,
+        )
+    }
+
+    /// Removes the secret and the recovery codes of a user, so that logins no longer require a
+    /// code.
+    pub fn delete_by_user_id<C>(user_id: i64, conn: &mut C) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        _ = RecoveryCode::delete_all_by_user_id(user_id, conn)?;
+        Ok(
+            0 != diesel::delete(user_totp::table.filter(user_totp::user_id.eq(user_id)))
+                .execute(conn)?,
+        )
+    }
+}
+
+/// A single-use code for logging in without the authenticator, stored hashed like a password.
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = user_recovery_codes, check_for_backend(Pg))]
+pub struct RecoveryCode {
+    pub id: i64,
+    pub user_id: i64,
+    pub code_hash: Password,
+    pub used_on: Option<DateTime<Utc>>,
+}
+
+impl RecoveryCode {
+    /// The number of codes given out at once.
+    pub const COUNT: usize = 10;
+
+    /// Replaces the codes of a user with new ones, returning them to be shown once.
+    pub fn generate_all<C>(
+        user_id: i64,
+        policy: &PasswordPolicy,
+        conn: &mut C,
+    ) -> Result<Vec<String>, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        let codes: Vec<_> = (0..Self::COUNT)
+            .map(|_| totp::generate_recovery_code())
+            .collect();
+        let hashes = codes
+            .iter()
+            .map(|code| Password::generate_current(code, None, policy))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        _ = Self::delete_all_by_user_id(user_id, conn)?;
+        _ = diesel::insert_into(user_recovery_codes::table)
+            .values(
+                hashes
+                    .iter()
+                    .map(|hash| {
+                        (
+                            user_recovery_codes::user_id.eq(user_id),
+                            user_recovery_codes::code_hash.eq(hash),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(conn)?;
+
+        Ok(codes)
+    }
+
+    /// Uses up the unused code of a user matching `code`. Returns whether there was one.
+    pub fn redeem<C>(
+        user_id: i64,
+        code: &str,
+        policy: &PasswordPolicy,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        let code = totp::normalize_recovery_code(code);
+        let unused: Vec<Self> = user_recovery_codes::table
+            .filter(user_recovery_codes::user_id.eq(user_id))
+            .filter(user_recovery_codes::used_on.is_null())
+            .select(user_recovery_codes::all_columns)
+            .load(conn)?;
+
+        let Some(matched) = unused
+            .into_iter()
+            .find(|unused| unused.code_hash.compare(&code, policy).unwrap_or(false))
+        else {
+            return Ok(false);
+        };
+
+        Ok(0 != diesel::update(
+            user_recovery_codes::table
+                .filter(user_recovery_codes::id.eq(matched.id))
+                .filter(user_recovery_codes::used_on.is_null()),
+        )
+        .set(user_recovery_codes::used_on.eq(now))
+        .execute(conn)?)
+    }
+
+    pub fn count_unused<C>(user_id: i64, conn: &mut C) -> Result<i64, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(user_recovery_codes::table
+            .filter(user_recovery_codes::user_id.eq(user_id))
+            .filter(user_recovery_codes::used_on.is_null())
+            .count()
+            .get_result(conn)?)
+    }
+
+    pub fn delete_all_by_user_id<C>(user_id: i64, conn: &mut C) -> Result<usize, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(diesel::delete(
+            user_recovery_codes::table.filter(user_recovery_codes::user_id.eq(user_id)),
+        )
+        .execute(conn)?)
+    }
+}
@@ -1,22 +1,24 @@
 use std::sync::RwLock;
 
 use base64::{prelude::BASE64_URL_SAFE, Engine};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use diesel::{
     connection::LoadConnection, pg::Pg, prelude::Queryable, BoolExpressionMethods, Connection,
     ExpressionMethods, Insertable, OptionalExtension, QueryDsl, RunQueryDsl, Selectable,
 };
+use hmac::{Hmac, Mac};
 use rand_chacha::ChaCha12Rng;
 use rand_core::{RngCore, SeedableRng};
+use sha3::Sha3_256;
 
 use crate::{
     auth::Password,
     db::Db,
-    schema::{user_sessions, users},
+    schema::{email_verifications, password_resets, user_sessions, users},
     Error,
 };
 
-#[derive(Selectable, Queryable)]
+#[derive(Clone, Selectable, Queryable)]
 #[diesel(check_for_backend(Pg))]
 pub struct User {
     pub id: i64,
@@ -25,9 +27,21 @@ pub struct User {
     pub password: Password,
     pub created_on: DateTime<Utc>,
     pub updated_on: DateTime<Utc>,
+    /// When the user proved owning `email`, if they did.
+    pub email_verified_on: Option<DateTime<Utc>>,
 }
 
 impl User {
+    pub fn by_id<C>(id: i64, conn: &mut C) -> Result<Option<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(users::table
+            .filter(users::id.eq(id))
+            .first(conn)
+            .optional()?)
+    }
+
     pub fn by_name<C>(name: &str, conn: &mut C) -> Result<Option<Self>, Error>
     where
         C: Connection<Backend = Pg> + LoadConnection,
@@ -59,14 +73,41 @@ impl User {
         )
     }
 
-    pub fn set_invalid<C>(self, conn: &mut C) -> Result<bool, Error>
+    pub fn is_email_verified(&self) -> bool {
+        self.email_verified_on.is_some()
+    }
+
+    /// Sets the address of the user to `email` and marks it as verified.
+    pub fn verify_email<C>(
+        &self,
+        email: &str,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<bool, Error>
     where
         C: Connection<Backend = Pg>,
     {
-        Ok(self.mark_updated(Utc::now(), conn)?
-            && 0 != diesel::update(users::table.filter(users::id.eq(self.id)))
-                .set(users::password.eq(Password::Invalid))
-                .execute(conn)?)
+        Ok(
+            0 != diesel::update(users::table.filter(users::id.eq(self.id)))
+                .set((
+                    users::email.eq(email),
+                    users::email_verified_on.eq(now),
+                    users::updated_on.eq(now),
+                ))
+                .execute(conn)?,
+        )
+    }
+
+    /// Replaces the stored password, e.g. with a stronger hash of the same password.
+    pub fn update_password<C>(&self, password: &Password, conn: &mut C) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(
+            0 != diesel::update(users::table.filter(users::id.eq(self.id)))
+                .set(users::password.eq(password))
+                .execute(conn)?,
+        )
     }
 }
 
@@ -75,8 +116,7 @@ impl User {
 pub struct NewUser<'a> {
     pub name: &'a str,
     pub email: &'a str,
-    #[diesel(serialize_as = Password)]
-    pub password: &'a str,
+    pub password: Password,
     pub created_on: DateTime<Utc>,
     pub updated_on: DateTime<Utc>,
 }
@@ -85,10 +125,10 @@ impl<'a> NewUser<'a> {
     pub fn new(
         name: &'a str,
         email: &'a str,
-        password: &'a str,
+        password: Password,
         created_on: Option<DateTime<Utc>>,
     ) -> Self {
-        let created_on = created_on.unwrap_or_else(|| Utc::now());
+        let created_on = created_on.unwrap_or_else(Utc::now);
         Self {
             name,
             email,
@@ -120,39 +160,154 @@ impl<'a> NewUser<'a> {
     }
 }
 
-#[derive(Queryable, Selectable, Insertable)]
+/// How login sessions last and how their tokens are kept.
+///
+/// A session expires once it has been idle for its idle lifetime, and in any case once its
+/// absolute lifetime has passed since logging in. Sessions where the user asked to be remembered
+/// use the longer lifetimes.
+#[derive(Clone)]
+pub struct SessionConfig {
+    pub idle: TimeDelta,
+    pub absolute: TimeDelta,
+    pub remembered_idle: TimeDelta,
+    pub remembered_absolute: TimeDelta,
+
+    /// The key for hashing session tokens before storing them, so that the stored hashes cannot
+    /// be used as tokens.
+    ///
+    /// Password reset and e-mail verification tokens are signed with it as well. A random key is
+    /// used when `SESSION_KEY` is unset, so all of these end when the server restarts.
+    pub key: Box<[u8]>,
+    /// Whether the session cookie is only sent over HTTPS; disable for local development over
+    /// plain HTTP.
+    pub secure_cookie: bool,
+}
+
+impl SessionConfig {
+    /// Generates a random key for hashing session tokens.
+    ///
+    /// Sessions hashed with a random key do not survive a restart, so a fixed key should be
+    /// configured in production.
+    pub fn random_key() -> Box<[u8]> {
+        let mut key = [0; 32];
+        ChaCha12Rng::from_os_rng().fill_bytes(&mut key);
+        Box::new(key)
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            idle: TimeDelta::hours(2),
+            absolute: TimeDelta::hours(12),
+            remembered_idle: TimeDelta::days(30),
+            remembered_absolute: TimeDelta::days(90),
+
+            key: Self::random_key(),
+            secure_cookie: true,
+        }
+    }
+}
+
+#[derive(Clone, Queryable, Selectable, Insertable)]
 #[diesel(table_name = user_sessions, check_for_backend(Pg))]
 pub struct Session {
-    pub token: String,
     pub user_id: i64,
+    /// When the session expires unless renewed by activity; sessions created by hand may have
+    /// no expiry.
     pub expire_on: Option<DateTime<Utc>>,
+    pub created_on: DateTime<Utc>,
+    /// Whether the user asked to stay logged in, selecting the longer lifetimes.
+    pub remember: bool,
+    /// The keyed hash of the session token; the token itself is only known to the browser.
+    pub token_hash: Vec<u8>,
 }
 
 impl Session {
-    pub fn from_token<C>(token: &str, conn: &mut C) -> Result<Option<Self>, Error>
+    pub fn from_token<C>(
+        token: &str,
+        config: &SessionConfig,
+        conn: &mut C,
+    ) -> Result<Option<Self>, Error>
     where
         C: Connection<Backend = Pg> + LoadConnection,
     {
         Ok(user_sessions::table
-            .filter(user_sessions::token.eq(token))
+            .filter(user_sessions::token_hash.eq(Self::hash_token(token, config)))
             .select(user_sessions::all_columns)
             .get_result(conn)
             .optional()?)
     }
 
+    /// Starts a new session, returning it along with its token for the session cookie.
     pub fn generate<C>(
         user_id: i64,
-        expire_on: Option<DateTime<Utc>>,
+        remember: bool,
+        config: &SessionConfig,
+        now: DateTime<Utc>,
         conn: &mut C,
-    ) -> Result<Self, Error>
+    ) -> Result<(Self, String), Error>
     where
         C: Connection<Backend = Pg> + LoadConnection,
     {
-        Ok(Self {
-            token: Self::new_token(conn)?,
+        let token = Self::new_token(config, conn)?;
+        let mut session = Self {
             user_id,
-            expire_on,
-        })
+            expire_on: None,
+            created_on: now,
+            remember,
+            token_hash: Self::hash_token(&token, config),
+        };
+        session.expire_on = Some(session.next_expiry(config, now));
+
+        Ok((session, token))
+    }
+
+    pub(crate) fn hash_token(token: &str, config: &SessionConfig) -> Vec<u8> {
+        let mut mac = Hmac::<Sha3_256>::new_from_slice(&config.key)
+            .expect("HMAC should accept keys of any length");
+        mac.update(token.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// Returns when the session would expire if it were used at `now`.
+    fn next_expiry(&self, config: &SessionConfig, now: DateTime<Utc>) -> DateTime<Utc> {
+        let (idle, absolute) = if self.remember {
+            (config.remembered_idle, config.remembered_absolute)
+        } else {
+            (config.idle, config.absolute)
+        };
+
+        (now + idle).min(self.created_on + absolute)
+    }
+
+    /// Extends the session after activity at `now`, up to its absolute lifetime.
+    ///
+    /// The session is only updated if its expiry would move by more than a minute, to avoid
+    /// writing on every request. Returns whether it was updated.
+    pub fn renew<C>(
+        &mut self,
+        config: &SessionConfig,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        let Some(expire_on) = self.expire_on else {
+            return Ok(false);
+        };
+        let next = self.next_expiry(config, now);
+        if next - expire_on < TimeDelta::minutes(1) {
+            return Ok(false);
+        }
+
+        self.expire_on = Some(next);
+        Ok(0 != diesel::update(
+            user_sessions::table.filter(user_sessions::token_hash.eq(&self.token_hash)),
+        )
+        .set(user_sessions::expire_on.eq(next))
+        .execute(conn)?)
     }
 
     pub fn insert<C>(self, conn: &mut C) -> Result<bool, Error>
@@ -165,7 +320,42 @@ impl Session {
             .execute(conn)?)
     }
 
-    fn new_token<C>(conn: &mut C) -> Result<String, Error>
+    /// Ends this session, returning whether it still existed.
+    pub fn delete<C>(&self, conn: &mut C) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(0 != diesel::delete(
+            user_sessions::table.filter(user_sessions::token_hash.eq(&self.token_hash)),
+        )
+        .execute(conn)?)
+    }
+
+    /// Ends all sessions of a user, on every device, returning how many there were.
+    pub fn delete_all_by_user_id<C>(user_id: i64, conn: &mut C) -> Result<usize, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(
+            diesel::delete(user_sessions::table.filter(user_sessions::user_id.eq(user_id)))
+                .execute(conn)?,
+        )
+    }
+
+    /// Ends all sessions of the same user except this one, returning how many there were.
+    pub fn delete_others<C>(&self, conn: &mut C) -> Result<usize, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(diesel::delete(
+            user_sessions::table
+                .filter(user_sessions::user_id.eq(self.user_id))
+                .filter(user_sessions::token_hash.ne(&self.token_hash)),
+        )
+        .execute(conn)?)
+    }
+
+    fn new_token<C>(config: &SessionConfig, conn: &mut C) -> Result<String, Error>
     where
         C: Connection<Backend = Pg> + LoadConnection,
     {
@@ -178,7 +368,7 @@ impl Session {
             TOKEN_RAND.with(|rand| rand.write().expect("RwLock poisoned").fill_bytes(&mut buf));
             let token = BASE64_URL_SAFE.encode(buf);
 
-            if Self::exists(&token, conn)? {
+            if Self::exists(&Self::hash_token(&token, config), conn)? {
                 continue;
             } else {
                 break token;
@@ -186,12 +376,12 @@ impl Session {
         })
     }
 
-    fn exists<C>(token: &str, conn: &mut C) -> Result<bool, Error>
+    fn exists<C>(token_hash: &[u8], conn: &mut C) -> Result<bool, Error>
     where
         C: Connection<Backend = Pg> + LoadConnection,
     {
         Ok(0 != user_sessions::table
-            .filter(user_sessions::token.eq(token))
+            .filter(user_sessions::token_hash.eq(token_hash))
             .count()
             .get_result::<i64>(conn)?)
     }
@@ -201,6 +391,175 @@ impl Session {
     }
 }
 
+/// A request to choose a new password, redeemed with a token sent to the user by e-mail.
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = password_resets, check_for_backend(Pg))]
+pub struct PasswordReset {
+    /// The keyed hash of the token, hashed like session tokens.
+    pub token_hash: Vec<u8>,
+    pub user_id: i64,
+    pub created_on: DateTime<Utc>,
+    pub expire_on: DateTime<Utc>,
+}
+
+impl PasswordReset {
+    /// How long the token sent by e-mail can be used.
+    pub const LIFETIME: TimeDelta = TimeDelta::hours(1);
+
+    /// Starts a reset for a user, replacing any earlier one, and returns the token to send.
+    pub fn generate<C>(
+        user_id: i64,
+        config: &SessionConfig,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<String, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        let token = random_token();
+
+        _ = Self::delete_all_by_user_id(user_id, conn)?;
+        _ = Self {
+            token_hash: Session::hash_token(&token, config),
+            user_id,
+            created_on: now,
+            expire_on: now + Self::LIFETIME,
+        }
+        .insert_into(password_resets::table)
+        .execute(conn)?;
+
+        Ok(token)
+    }
+
+    /// Finds the reset for `token` if it has not expired yet.
+    pub fn from_token<C>(
+        token: &str,
+        config: &SessionConfig,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<Option<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(password_resets::table
+            .filter(password_resets::token_hash.eq(Session::hash_token(token, config)))
+            .filter(password_resets::expire_on.gt(now))
+            .select(password_resets::all_columns)
+            .get_result(conn)
+            .optional()?)
+    }
+
+    /// Redeems the reset, so that its token cannot be used again. Returns whether it was still
+    /// unused.
+    pub fn redeem<C>(&self, conn: &mut C) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(0 != diesel::delete(
+            password_resets::table.filter(password_resets::token_hash.eq(&self.token_hash)),
+        )
+        .execute(conn)?)
+    }
+
+    pub fn delete_all_by_user_id<C>(user_id: i64, conn: &mut C) -> Result<usize, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(
+            diesel::delete(password_resets::table.filter(password_resets::user_id.eq(user_id)))
+                .execute(conn)?,
+        )
+    }
+}
+
+/// A request to verify an e-mail address, redeemed with a token sent to that address.
+///
+/// The address only becomes the address of the user once verified, so that changing it to an
+/// address one does not own has no effect.
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = email_verifications, check_for_backend(Pg))]
+pub struct EmailVerification {
+    /// The keyed hash of the token, hashed like session tokens.
+    pub token_hash: Vec<u8>,
+    pub user_id: i64,
+    pub email: String,
+    pub created_on: DateTime<Utc>,
+    pub expire_on: DateTime<Utc>,
+}
+
+impl EmailVerification {
+    /// How long the token sent by e-mail can be used.
+    pub const LIFETIME: TimeDelta = TimeDelta::days(2);
+
+    /// Starts verifying `email` for a user, replacing any earlier verification, and returns the
+    /// token to send.
+    pub fn generate<C>(
+        user_id: i64,
+        email: &str,
+        config: &SessionConfig,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<String, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        let token = random_token();
+
+        _ = diesel::delete(
+            email_verifications::table.filter(email_verifications::user_id.eq(user_id)),
+        )
+        .execute(conn)?;
+        _ = Self {
+            token_hash: Session::hash_token(&token, config),
+            user_id,
+            email: email.to_string(),
+            created_on: now,
+            expire_on: now + Self::LIFETIME,
+        }
+        .insert_into(email_verifications::table)
+        .execute(conn)?;
+
+        Ok(token)
+    }
+
+    /// Finds the verification for `token` if it has not expired yet.
+    pub fn from_token<C>(
+        token: &str,
+        config: &SessionConfig,
+        now: DateTime<Utc>,
+        conn: &mut C,
+    ) -> Result<Option<Self>, Error>
+    where
+        C: Connection<Backend = Pg> + LoadConnection,
+    {
+        Ok(email_verifications::table
+            .filter(email_verifications::token_hash.eq(Session::hash_token(token, config)))
+            .filter(email_verifications::expire_on.gt(now))
+            .select(email_verifications::all_columns)
+            .get_result(conn)
+            .optional()?)
+    }
+
+    /// Redeems the verification, so that its token cannot be used again. Returns whether it was
+    /// still unused.
+    pub fn redeem<C>(&self, conn: &mut C) -> Result<bool, Error>
+    where
+        C: Connection<Backend = Pg>,
+    {
+        Ok(0 != diesel::delete(
+            email_verifications::table.filter(email_verifications::token_hash.eq(&self.token_hash)),
+        )
+        .execute(conn)?)
+    }
+}
+
+/// Generates a token to send by e-mail, long enough to not need checking for collisions.
+fn random_token() -> String {
+    let mut buf = [0; 32];
+    ChaCha12Rng::from_os_rng().fill_bytes(&mut buf);
+    BASE64_URL_SAFE.encode(buf)
+}
+
 pub fn cleanup_sessions(db: &Db, now: DateTime<Utc>) -> Result<bool, Error> {
     let mut conn = db.pool.get()?;
     Ok(0 != diesel::delete(
@@ -212,3 +571,39 @@ pub fn cleanup_sessions(db: &Db, now: DateTime<Utc>) -> Result<bool, Error> {
     )
     .execute(&mut conn)?)
 }
+
+#[test]
+fn session_expiry_test() {
+    let config = SessionConfig::default();
+    let login = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    let mut session = Session {
+        user_id: 1,
+        expire_on: None,
+        created_on: login,
+        remember: false,
+        token_hash: Vec::new(),
+    };
+
+    assert_eq!(session.next_expiry(&config, login), login + config.idle);
+    // activity keeps the session alive, but never past its absolute lifetime
+    let later = login + TimeDelta::hours(11);
+    assert_eq!(session.next_expiry(&config, later), login + config.absolute);
+
+    session.remember = true;
+    assert_eq!(
+        session.next_expiry(&config, later),
+        later + config.remembered_idle
+    );
+}
+
+#[test]
+fn session_token_hash_test() {
+    let config = SessionConfig::default();
+    let hash = Session::hash_token("token", &config);
+    assert_eq!(hash, Session::hash_token("token", &config));
+    assert_ne!(hash, Session::hash_token("other", &config));
+
+    // a leaked hash is useless without the key
+    let other = SessionConfig::default();
+    assert_ne!(hash, Session::hash_token("token", &other));
+}
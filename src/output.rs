@@ -11,6 +11,7 @@ use diesel::{
 #[diesel(sql_type = Binary, check_for_backend(Pg))]
 pub enum Body {
     Text(String),
+    Redirect(String),
 }
 
 impl Body {
@@ -18,6 +19,8 @@ impl Body {
         Ok(if body.starts_with(b":") {
             if body.starts_with(b":text:") {
                 Self::Text(String::from_utf8(body)?.split_off(":text:".len()))
+            } else if body.starts_with(b":redirect:") {
+                Self::Redirect(String::from_utf8(body)?.split_off(":redirect:".len()))
             } else {
                 return Err(PageError::Invalid);
             }
@@ -26,17 +29,137 @@ impl Body {
         })
     }
 
+    /// Parses the source text submitted through the page editor.
+    ///
+    /// A source of the form `#REDIRECT [[Target]]` (case-insensitive keyword) becomes a redirect to
+    /// `Target`; anything else is kept as plain text.
+    pub fn from_source(source: String) -> Self {
+        Self::parse_redirect(&source)
+            .map(|target| Self::Redirect(target.to_string()))
+            .unwrap_or(Self::Text(source))
+    }
+
+    pub fn as_redirect(&self) -> Option<&str> {
+        match self {
+            Self::Redirect(target) => Some(target),
+            _ => None,
+        }
+    }
+
+    /// Returns the source text of the body, as it would be shown in the page editor.
     pub fn into_text(self) -> String {
         match self {
             Self::Text(text) => text,
+            Self::Redirect(target) => format!("#REDIRECT [[{target}]]"),
+        }
+    }
+
+    /// Returns the targets of all `[[Target]]` and `[[Target|label]]` links in the body, in order.
+    ///
+    /// A redirect links only to its target.
+    pub fn links(&self) -> Vec<&str> {
+        let mut text = match self {
+            Self::Text(text) => text.as_str(),
+            Self::Redirect(target) => return vec![target],
+        };
+
+        let mut links = Vec::new();
+        while let Some(start) = text.find("[[") {
+            text = &text[start + "[[".len()..];
+            let Some(end) = text.find("]]") else {
+                break;
+            };
+
+            let inner = &text[..end];
+            // an unclosed link is not a link, but may be followed by one
+            if let Some(nested) = inner.rfind("[[") {
+                text = &text[nested..];
+                continue;
+            }
+            let target = inner
+                .split_once('|')
+                .map_or(inner, |(target, _)| target)
+                .trim();
+            if !target.is_empty() {
+                links.push(target);
+            }
+            text = &text[end + "]]".len()..];
         }
+
+        links
+    }
+
+    /// Returns the TeX source of all `$inline$` and `$$display$$` formulas in the body, in order.
+    ///
+    /// A dollar sign escaped as `\$` does not start or end a formula.
+    pub fn formulas(&self) -> Vec<&str> {
+        let Self::Text(text) = self else {
+            return Vec::new();
+        };
+
+        let mut formulas = Vec::new();
+        let mut rest = text.as_str();
+        while let Some(start) = find_unescaped(rest, "$") {
+            let delimiter = if rest[start..].starts_with("$$") {
+                "$$"
+            } else {
+                "$"
+            };
+            rest = &rest[start + delimiter.len()..];
+            let Some(end) = find_unescaped(rest, delimiter) else {
+                break;
+            };
+
+            let formula = rest[..end].trim();
+            if !formula.is_empty() {
+                formulas.push(formula);
+            }
+            rest = &rest[end + delimiter.len()..];
+        }
+
+        formulas
+    }
+
+    fn parse_redirect(source: &str) -> Option<&str> {
+        let source = source.trim();
+        let keyword = source.get(.."#REDIRECT".len())?;
+        if !keyword.eq_ignore_ascii_case("#REDIRECT") {
+            return None;
+        }
+
+        let target = source["#REDIRECT".len()..]
+            .trim_start()
+            .strip_prefix("[[")?
+            .strip_suffix("]]")?
+            .trim();
+        (!target.is_empty() && !target.contains(['[', ']'])).then_some(target)
     }
 }
 
+/// Finds the first occurrence of `pattern` in `text` which is not preceded by a backslash.
+fn find_unescaped(text: &str, pattern: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(found) = text[offset..].find(pattern) {
+        let index = offset + found;
+        let backslashes = text[..index]
+            .bytes()
+            .rev()
+            .take_while(|&b| b == b'\\')
+            .count();
+        if backslashes % 2 == 0 {
+            return Some(index);
+        }
+        offset = index + pattern.len();
+    }
+
+    None
+}
+
 impl std::fmt::Display for Body {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Body::Text(text) => write!(f, ":text:{text}"),
+            Body::Redirect(target) => write!(f, ":redirect:{target}"),
         }
     }
 }
@@ -73,3 +196,44 @@ pub enum PageError {
     #[error(transparent)]
     FromUtf8(#[from] std::string::FromUtf8Error),
 }
+
+#[test]
+fn redirect_source_test() {
+    let body = Body::from_source("#redirect  [[Pythagorean theorem]]\n".to_string());
+    assert_eq!(body.as_redirect(), Some("Pythagorean theorem"));
+    assert_eq!(body.into_text(), "#REDIRECT [[Pythagorean theorem]]");
+
+    assert!(Body::from_source("#REDIRECT [[]]".to_string())
+        .as_redirect()
+        .is_none());
+    assert!(Body::from_source("see [[Pythagorean theorem]]".to_string())
+        .as_redirect()
+        .is_none());
+}
+
+#[test]
+fn links_test() {
+    let body = Body::from_source(
+        "See [[Euclid]] and [[Pythagorean theorem|the theorem]], [[ ]] [[unclosed [[Category:Geometry]]"
+            .to_string(),
+    );
+    assert_eq!(
+        body.links(),
+        ["Euclid", "Pythagorean theorem", "Category:Geometry"]
+    );
+
+    let body = Body::from_source("#REDIRECT [[Euclid]]".to_string());
+    assert_eq!(body.links(), ["Euclid"]);
+}
+
+#[test]
+fn formulas_test() {
+    let body = Body::from_source(
+        r"Costs \$5. Inline $a^2 + b^2 = c^2$, display $$ \int_a^b f(x)\,dx $$, $\$$ and $unclosed"
+            .to_string(),
+    );
+    assert_eq!(
+        body.formulas(),
+        [r"a^2 + b^2 = c^2", r"\int_a^b f(x)\,dx", r"\$"]
+    );
+}
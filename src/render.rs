@@ -2,6 +2,7 @@ use tera::{Context, Tera};
 
 use crate::{
     asset::{Assets, Loc},
+    controllers::wiki::csrf,
     Error,
 };
 
@@ -16,25 +17,76 @@ impl Renderer {
         })
     }
 
+    /// Renders the template `name`, adding the CSRF token of the current request for its forms.
     pub fn render(&self, name: &str, context: &Context) -> Result<String, Error> {
-        Ok(self.tera.render(name, context)?)
+        let mut context = context.clone();
+        context.insert("csrf_token", &csrf::current_token().unwrap_or_default());
+        Ok(self.tera.render(name, &context)?)
     }
 }
 
 impl Renderer {
     fn new_tera(assets: &Assets) -> Result<Tera, Error> {
         let mut tera = Tera::default();
+        // templates are named without their `.html` suffix, so escaping is turned on for all of
+        // them rather than by extension
+        tera.autoescape_on(vec![""]);
         tera.add_raw_templates(vec![
             Self::load_template(
                 assets,
                 "index".to_string(),
                 "templates/index.html.tera".to_string(),
             )?,
+            Self::load_template(
+                assets,
+                "email".to_string(),
+                "templates/email.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "groups".to_string(),
+                "templates/groups.html.tera".to_string(),
+            )?,
             Self::load_template(
                 assets,
                 "login".to_string(),
                 "templates/login.html.tera".to_string(),
             )?,
+            Self::load_template(
+                assets,
+                "login/verify".to_string(),
+                "templates/login/verify.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "logout".to_string(),
+                "templates/logout.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "password".to_string(),
+                "templates/password.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "reset".to_string(),
+                "templates/reset.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "signup".to_string(),
+                "templates/signup.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "totp".to_string(),
+                "templates/totp.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "csrf".to_string(),
+                "templates/csrf.html.tera".to_string(),
+            )?,
             Self::load_template(
                 assets,
                 "page/base".to_string(),
@@ -55,6 +107,106 @@ impl Renderer {
                 "page/not-found".to_string(),
                 "templates/page/not-found.html.tera".to_string(),
             )?,
+            Self::load_template(
+                assets,
+                "page/redirect".to_string(),
+                "templates/page/redirect.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "page/delete".to_string(),
+                "templates/page/delete.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "page/undelete".to_string(),
+                "templates/page/undelete.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "page/deleted".to_string(),
+                "templates/page/deleted.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "page/permission-denied".to_string(),
+                "templates/page/permission-denied.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "page/unverified".to_string(),
+                "templates/page/unverified.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "page/protect".to_string(),
+                "templates/page/protect.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "page/protected".to_string(),
+                "templates/page/protected.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "page/talk".to_string(),
+                "templates/page/talk.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "page/category".to_string(),
+                "templates/page/category.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "page/bad-title".to_string(),
+                "templates/page/bad-title.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "special/not-found".to_string(),
+                "templates/special/not-found.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "special/special-pages".to_string(),
+                "templates/special/special-pages.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "special/all-pages".to_string(),
+                "templates/special/all-pages.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "special/random-page".to_string(),
+                "templates/special/random-page.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "special/new-pages".to_string(),
+                "templates/special/new-pages.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "special/long-pages".to_string(),
+                "templates/special/long-pages.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "special/search".to_string(),
+                "templates/special/search.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "special/report".to_string(),
+                "templates/special/report.html.tera".to_string(),
+            )?,
+            Self::load_template(
+                assets,
+                "special/redirects".to_string(),
+                "templates/special/redirects.html.tera".to_string(),
+            )?,
         ])?;
 
         Ok(tera)
@@ -73,3 +225,24 @@ impl Renderer {
         ))
     }
 }
+
+#[test]
+fn render_escape_test() {
+    let config = crate::app::Config::builder()
+        .with_server_url("127.0.0.1:3000".to_string())
+        .with_database_url("postgres://localhost/euclidon".to_string())
+        .with_session_key(Box::from(*b"render-test-key"))
+        .build()
+        .unwrap();
+    let renderer = Renderer::new(&Assets::new(&config)).unwrap();
+
+    let html = renderer
+        .render(
+            "password",
+            &Context::from_serialize(serde_json::json!({ "error": "Tom & <Jerry> \"Cat\"" }))
+                .unwrap(),
+        )
+        .unwrap();
+    // values are escaped once by the global autoescaping, not again by a filter
+    assert!(html.contains("Tom &amp; &lt;Jerry&gt; &quot;Cat&quot;"));
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use axum::{routing::get, Router};
+use axum::{middleware, routing::get, Router};
 
 use crate::{
     controllers::{assets, root, wiki},
@@ -8,9 +8,24 @@ use crate::{
 };
 
 pub fn build_router(app: Arc<App>) -> Router {
+    let state = AppState(app);
     build_base_router()
-        .nest("/w", build_wiki_router())
-        .with_state(AppState(app))
+        .nest(
+            "/w",
+            build_wiki_router()
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    wiki::viewer::load_viewer,
+                ))
+                .layer(middleware::from_fn_with_state(
+                    state.clone(),
+                    wiki::csrf::provide_token,
+                ))
+                .layer(middleware::map_response(
+                    wiki::special::keep_cookies_private,
+                )),
+        )
+        .with_state(state)
 }
 
 fn build_base_router() -> Router<AppState> {
@@ -22,6 +37,21 @@ fn build_base_router() -> Router<AppState> {
 fn build_wiki_router() -> Router<AppState> {
     Router::new()
         .route("/", get(root::get))
+        .route("/api/suggest", get(wiki::api::suggest))
+        .route("/email", get(wiki::email::get).post(wiki::email::post))
+        .route("/groups", get(wiki::groups::get).post(wiki::groups::post))
         .route("/login", get(wiki::login::get).post(wiki::login::post))
+        .route(
+            "/login/verify",
+            get(wiki::login::get_verify).post(wiki::login::post_verify),
+        )
+        .route("/logout", get(wiki::logout::get).post(wiki::logout::post))
+        .route(
+            "/password",
+            get(wiki::password::get).post(wiki::password::post),
+        )
+        .route("/reset", get(wiki::reset::get).post(wiki::reset::post))
+        .route("/signup", get(wiki::signup::get).post(wiki::signup::post))
+        .route("/totp", get(wiki::totp::get).post(wiki::totp::post))
         .route("/page/{*path}", get(wiki::page::get).post(wiki::page::post))
 }
@@ -1,5 +1,49 @@
 // @generated automatically by Diesel CLI.
 
+pub mod sql_types {
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "tsvector", schema = "pg_catalog"))]
+    pub struct Tsvector;
+}
+
+diesel::table! {
+    archived_pages (id) {
+        id -> Int8,
+        page_id -> Int8,
+        #[max_length = 255]
+        title -> Varchar,
+        rev_id -> Int8,
+        root_id -> Int8,
+        created_on -> Timestamptz,
+        deleted_by -> Int8,
+        deleted_on -> Timestamptz,
+        reason -> Text,
+        namespace -> Int2,
+    }
+}
+
+diesel::table! {
+    archived_revisions (id) {
+        id -> Int8,
+        archive_id -> Int8,
+        parent_id -> Nullable<Int8>,
+        content_id -> Int8,
+        user_id -> Int8,
+        created_on -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    cached_reports (report, namespace, title) {
+        report -> Int2,
+        namespace -> Int2,
+        #[max_length = 255]
+        title -> Varchar,
+        value -> Int8,
+        computed_on -> Timestamptz,
+    }
+}
+
 diesel::table! {
     contents (id) {
         id -> Int8,
@@ -7,6 +51,112 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    discussion_posts (id) {
+        id -> Int8,
+        thread_id -> Int8,
+        parent_id -> Nullable<Int8>,
+        body -> Text,
+        user_id -> Int8,
+        created_on -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    discussion_threads (id) {
+        id -> Int8,
+        namespace -> Int2,
+        #[max_length = 255]
+        title -> Varchar,
+        #[max_length = 255]
+        subject -> Varchar,
+        user_id -> Int8,
+        created_on -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    email_verifications (token_hash) {
+        token_hash -> Bytea,
+        user_id -> Int8,
+        #[max_length = 320]
+        email -> Varchar,
+        created_on -> Timestamptz,
+        expire_on -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    login_attempts (id) {
+        id -> Int8,
+        user_id -> Nullable<Int8>,
+        login -> Text,
+        address -> Text,
+        success -> Bool,
+        attempted_on -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    page_categories (page_id, category) {
+        page_id -> Int8,
+        #[max_length = 255]
+        category -> Varchar,
+    }
+}
+
+diesel::table! {
+    page_formulas (id) {
+        id -> Int8,
+        page_id -> Int8,
+        source -> Text,
+        tokens -> Text,
+        ngrams -> Array<Text>,
+    }
+}
+
+diesel::table! {
+    page_links (page_id, target_namespace, target) {
+        page_id -> Int8,
+        target_namespace -> Int2,
+        #[max_length = 255]
+        target -> Varchar,
+    }
+}
+
+diesel::table! {
+    page_protections (page_id, action) {
+        page_id -> Int8,
+        action -> Int2,
+        level -> Int2,
+        expire_on -> Nullable<Timestamptz>,
+        reason -> Text,
+        user_id -> Int8,
+        created_on -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    page_redirects (page_id) {
+        page_id -> Int8,
+        #[max_length = 255]
+        target -> Varchar,
+        target_namespace -> Int2,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::Tsvector;
+
+    page_search (page_id) {
+        page_id -> Int8,
+        title -> Text,
+        body -> Text,
+        vector -> Tsvector,
+    }
+}
+
 diesel::table! {
     pages (id) {
         id -> Int8,
@@ -15,6 +165,25 @@ diesel::table! {
         rev_id -> Int8,
         root_id -> Int8,
         created_on -> Timestamptz,
+        namespace -> Int2,
+    }
+}
+
+diesel::table! {
+    password_resets (token_hash) {
+        token_hash -> Bytea,
+        user_id -> Int8,
+        created_on -> Timestamptz,
+        expire_on -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    reset_requests (id) {
+        id -> Int8,
+        user_id -> Nullable<Int8>,
+        address -> Text,
+        requested_on -> Timestamptz,
     }
 }
 
@@ -29,11 +198,38 @@ diesel::table! {
 }
 
 diesel::table! {
-    user_sessions (token) {
-        #[max_length = 24]
-        token -> Varchar,
+    user_groups (user_id, group_name) {
+        user_id -> Int8,
+        group_name -> Text,
+        added_on -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    user_recovery_codes (id) {
+        id -> Int8,
+        user_id -> Int8,
+        code_hash -> Bytea,
+        used_on -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    user_sessions (token_hash) {
         user_id -> Int8,
         expire_on -> Nullable<Timestamptz>,
+        created_on -> Timestamptz,
+        remember -> Bool,
+        token_hash -> Bytea,
+    }
+}
+
+diesel::table! {
+    user_totp (user_id) {
+        user_id -> Int8,
+        secret -> Bytea,
+        enabled_on -> Nullable<Timestamptz>,
+        last_step -> Int8,
     }
 }
 
@@ -47,17 +243,57 @@ diesel::table! {
         password -> Bytea,
         created_on -> Timestamptz,
         updated_on -> Timestamptz,
+        email_verified_on -> Nullable<Timestamptz>,
     }
 }
 
+diesel::joinable!(archived_pages -> users (deleted_by));
+diesel::joinable!(archived_revisions -> archived_pages (archive_id));
+diesel::joinable!(archived_revisions -> contents (content_id));
+diesel::joinable!(archived_revisions -> users (user_id));
+diesel::joinable!(discussion_posts -> discussion_threads (thread_id));
+diesel::joinable!(discussion_posts -> users (user_id));
+diesel::joinable!(discussion_threads -> users (user_id));
+diesel::joinable!(email_verifications -> users (user_id));
+diesel::joinable!(login_attempts -> users (user_id));
+diesel::joinable!(page_categories -> pages (page_id));
+diesel::joinable!(page_formulas -> pages (page_id));
+diesel::joinable!(page_links -> pages (page_id));
+diesel::joinable!(page_protections -> pages (page_id));
+diesel::joinable!(page_protections -> users (user_id));
+diesel::joinable!(page_redirects -> pages (page_id));
+diesel::joinable!(page_search -> pages (page_id));
+diesel::joinable!(password_resets -> users (user_id));
+diesel::joinable!(reset_requests -> users (user_id));
 diesel::joinable!(revisions -> contents (content_id));
 diesel::joinable!(revisions -> users (user_id));
+diesel::joinable!(user_groups -> users (user_id));
+diesel::joinable!(user_recovery_codes -> users (user_id));
 diesel::joinable!(user_sessions -> users (user_id));
+diesel::joinable!(user_totp -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    archived_pages,
+    archived_revisions,
+    cached_reports,
     contents,
+    discussion_posts,
+    discussion_threads,
+    email_verifications,
+    login_attempts,
+    page_categories,
+    page_formulas,
+    page_links,
+    page_protections,
+    page_redirects,
+    page_search,
     pages,
+    password_resets,
+    reset_requests,
     revisions,
+    user_groups,
+    user_recovery_codes,
     user_sessions,
+    user_totp,
     users,
 );
@@ -3,9 +3,13 @@ use std::{sync::Arc, time::Duration};
 use chrono::Utc;
 use tokio::time::sleep;
 
-use crate::{model::user, App};
+use crate::{
+    model::{login, report, search, user},
+    App,
+};
 
-const SECONDS_IN_MONTH: u64 = 30 * 24 * 3600;
+const SECONDS_IN_HOUR: u64 = 3600;
+const SECONDS_IN_DAY: u64 = 24 * SECONDS_IN_HOUR;
 
 pub async fn cleanup_table_user_sessions(app: Arc<App>) {
     loop {
@@ -13,6 +17,33 @@ pub async fn cleanup_table_user_sessions(app: Arc<App>) {
             println!("Error occured during cleanup of user_sessions table: {e:?}");
         }
 
-        sleep(Duration::from_secs(SECONDS_IN_MONTH)).await;
+        // sessions expire after hours of idling, so they are swept often to keep the table small
+        sleep(Duration::from_secs(SECONDS_IN_HOUR)).await;
+    }
+}
+
+pub async fn cleanup_table_login_attempts(app: Arc<App>) {
+    loop {
+        if let Err(e) = login::cleanup_attempts(&app.db, Utc::now()) {
+            println!("Error occured during cleanup of login_attempts table: {e:?}");
+        }
+
+        sleep(Duration::from_secs(SECONDS_IN_DAY)).await;
+    }
+}
+
+pub async fn refresh_table_cached_reports(app: Arc<App>) {
+    loop {
+        if let Err(e) = report::refresh_reports(&app.db, Utc::now()) {
+            println!("Error occured during refresh of cached_reports table: {e:?}");
+        }
+
+        sleep(Duration::from_secs(SECONDS_IN_DAY)).await;
+    }
+}
+
+pub async fn index_table_page_formulas(app: Arc<App>) {
+    if let Err(e) = search::index_formulas(&app.db) {
+        println!("Error occured during indexing of page_formulas table: {e:?}");
     }
 }
@@ -7,5 +7,10 @@ use tokio::task::JoinHandle;
 use crate::App;
 
 pub fn spawn_tasks(app: Arc<App>) -> Vec<JoinHandle<()>> {
-    vec![tokio::spawn(db::cleanup_table_user_sessions(app.clone()))]
+    vec![
+        tokio::spawn(db::cleanup_table_user_sessions(app.clone())),
+        tokio::spawn(db::cleanup_table_login_attempts(app.clone())),
+        tokio::spawn(db::refresh_table_cached_reports(app.clone())),
+        tokio::spawn(db::index_table_page_formulas(app.clone())),
+    ]
 }
@@ -0,0 +1,296 @@
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use serde_json::json;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::model::namespace::Namespace;
+
+/// The maximum length of a title in characters, without its namespace prefix, as stored in
+/// `pages.title`.
+pub const MAX_TITLE_LENGTH: usize = 255;
+
+/// Characters which may not appear in titles, besides control characters.
+///
+/// These would be ambiguous in link syntax such as `[[Target#Section|label]]`.
+pub const FORBIDDEN_CHARS: [char; 8] = ['#', '<', '>', '[', ']', '|', '{', '}'];
+
+/// Characters which are percent-encoded when a title or fragment is put into a URL, so that it
+/// stays a single path segment and cannot start a query or fragment.
+///
+/// Non-ASCII characters are always encoded as well.
+pub const URL_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'+')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Percent-encodes `value` for use in a URL path or fragment.
+pub fn encode_url(value: &str) -> String {
+    utf8_percent_encode(value, URL_ENCODE_SET).to_string()
+}
+
+/// Site-wide rules for normalizing titles.
+#[derive(Debug, Clone, Default)]
+pub struct TitleConfig {
+    /// Whether the first letter of every title is made uppercase, so that `pythagorean theorem`
+    /// and `Pythagorean theorem` name the same page.
+    pub capitalize: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TitleError {
+    #[error("the title is empty")]
+    Empty,
+    #[error("the title is {0} characters long, but at most {MAX_TITLE_LENGTH} are allowed")]
+    TooLong(usize),
+    #[error("the title contains the character {0:?}, which is not allowed in titles")]
+    ForbiddenChar(char),
+    #[error("the title is not valid UTF-8 after percent-decoding")]
+    InvalidEncoding,
+}
+
+fn is_forbidden(c: char) -> bool {
+    c.is_control() || FORBIDDEN_CHARS.contains(&c)
+}
+
+/// The title of a page, split into its namespace and its name within that namespace.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PageTitle {
+    pub namespace: Namespace,
+    /// The name in display form, i.e. with spaces and without the namespace prefix.
+    pub name: String,
+}
+
+impl PageTitle {
+    pub fn new(namespace: Namespace, name: String) -> Self {
+        Self { namespace, name }
+    }
+
+    /// Normalizes a raw title which is already decoded, such as one from a path or a form,
+    /// recognising its namespace prefix.
+    ///
+    /// The title is brought into Unicode NFC form, and runs of whitespace and underscores are
+    /// collapsed into single spaces. An unknown prefix, or a known prefix with
+    /// nothing after it, is kept as part of the name. Slashes separate subpages from their parents,
+    /// so empty segments between them are dropped.
+    pub fn parse(raw: &str, config: &TitleConfig) -> Result<Self, TitleError> {
+        let normalized = raw.nfc().collect::<String>();
+        if let Some(c) = normalized.chars().find(|&c| is_forbidden(c)) {
+            return Err(TitleError::ForbiddenChar(c));
+        }
+
+        let display = normalized
+            .replace('_', " ")
+            .split('/')
+            .map(|segment| segment.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let (namespace, name) = match display.split_once(':') {
+            Some((prefix, name)) if !name.trim().is_empty() => {
+                match Namespace::from_prefix(prefix.trim()) {
+                    Some(namespace) => (namespace, name.trim().to_string()),
+                    None => (Namespace::Main, display),
+                }
+            }
+            _ => (Namespace::Main, display),
+        };
+        if name.is_empty() {
+            return Err(TitleError::Empty);
+        }
+
+        let name = if config.capitalize {
+            let mut chars = name.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        } else {
+            name
+        };
+
+        let length = name.chars().count();
+        if length > MAX_TITLE_LENGTH {
+            return Err(TitleError::TooLong(length));
+        }
+
+        Ok(Self::new(namespace, name))
+    }
+
+    /// Normalizes a link or redirect target from page text, which may be percent-encoded as in
+    /// `[[%C3%89cole]]`.
+    pub fn parse_link(raw: &str, config: &TitleConfig) -> Result<Self, TitleError> {
+        let decoded = percent_decode_str(raw)
+            .decode_utf8()
+            .map_err(|_| TitleError::InvalidEncoding)?;
+        Self::parse(&decoded, config)
+    }
+
+    /// Constructs a title from its namespace and its name as stored in the database.
+    pub fn from_stored(namespace: Namespace, key: &str) -> Self {
+        Self::new(namespace, key.replace('_', " "))
+    }
+
+    /// The name as stored in the database, i.e. in query form and without the namespace prefix.
+    pub fn key(&self) -> String {
+        self.name.replace(char::is_whitespace, "_")
+    }
+
+    /// The full title as displayed to users.
+    pub fn display(&self) -> String {
+        match self.namespace.prefix() {
+            Some(prefix) => format!("{prefix}:{}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// The full title as used in URLs, percent-encoded.
+    pub fn query(&self) -> String {
+        encode_url(&self.display().replace(char::is_whitespace, "_"))
+    }
+
+    /// The talk page associated with this page, if its namespace has talk pages.
+    pub fn talk(&self) -> Option<Self> {
+        self.namespace
+            .talk()
+            .map(|namespace| Self::new(namespace, self.name.clone()))
+    }
+
+    /// The page this talk page is about, if this is a talk page.
+    pub fn subject(&self) -> Option<Self> {
+        self.namespace
+            .subject()
+            .map(|namespace| Self::new(namespace, self.name.clone()))
+    }
+
+    /// The parent pages of this subpage, from the outermost inwards.
+    pub fn parents(&self) -> Vec<Self> {
+        self.name
+            .match_indices('/')
+            .map(|(i, _)| Self::new(self.namespace, self.name[..i].to_string()))
+            .collect()
+    }
+
+    /// The last segment of the name, which is the whole name unless this is a subpage.
+    pub fn base_name(&self) -> &str {
+        self.name.rsplit('/').next().unwrap_or(&self.name)
+    }
+
+    /// The title as passed into templates, along with its associated talk or subject page.
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "display": self.display(),
+            "query": self.query(),
+            "namespace": self.namespace.name(),
+            "talk": self.talk().as_ref().map(Self::to_link_json),
+            "subject": self.subject().as_ref().map(Self::to_link_json),
+            "parents": self.parents().iter().map(Self::to_link_json).collect::<Vec<_>>(),
+        })
+    }
+
+    fn to_link_json(&self) -> serde_json::Value {
+        json!({
+            "display": self.display(),
+            "query": self.query(),
+            "base_name": self.base_name(),
+        })
+    }
+}
+
+#[test]
+fn page_title_test() {
+    let config = TitleConfig::default();
+    let parse = |raw: &str| PageTitle::parse(raw, &config).unwrap();
+
+    let title = parse("talk: Pythagorean_theorem ");
+    assert_eq!(title.namespace, Namespace::Talk);
+    assert_eq!(title.display(), "Talk:Pythagorean theorem");
+    assert_eq!(title.query(), "Talk:Pythagorean_theorem");
+    assert_eq!(title.key(), "Pythagorean_theorem");
+    assert_eq!(title.subject(), Some(parse("Pythagorean theorem")));
+
+    let title = parse("user_talk:Euclid");
+    assert_eq!(title.namespace, Namespace::UserTalk);
+    assert_eq!(title.query(), "User_talk:Euclid");
+    assert_eq!(title.subject(), Some(parse("User:Euclid")));
+    assert_eq!(
+        parse("Help:Editing").talk(),
+        Some(parse("Help talk:Editing"))
+    );
+    assert_eq!(parse("Talk:Euclid").talk(), None);
+
+    let title = parse("Theorem: Pythagoras");
+    assert_eq!(title.namespace, Namespace::Main);
+    assert_eq!(title.query(), "Theorem:_Pythagoras");
+
+    assert_eq!(parse("Help:").namespace, Namespace::Main);
+
+    let title = parse("Group / Sylow_theorems//Proof/");
+    assert_eq!(title.query(), "Group/Sylow_theorems/Proof");
+    assert_eq!(title.base_name(), "Proof");
+    assert_eq!(
+        title.parents(),
+        [parse("Group"), parse("Group/Sylow theorems")]
+    );
+
+    // titles from paths are already decoded, and are encoded again for URLs
+    let title = parse("100%_a?b&c");
+    assert_eq!(title.display(), "100% a?b&c");
+    assert_eq!(title.query(), "100%25_a%3Fb%26c");
+    assert_eq!(parse("%41").query(), "%2541");
+    assert_eq!(parse("\u{e9}cole").query(), "%C3%A9cole");
+}
+
+#[test]
+fn title_normalization_test() {
+    let config = TitleConfig { capitalize: true };
+    let parse = |raw: &str| PageTitle::parse(raw, &config);
+
+    assert_eq!(
+        PageTitle::parse_link("help:%C3%A9cole  _normale", &config)
+            .unwrap()
+            .display(),
+        "Help:\u{c9}cole normale"
+    );
+    assert_eq!(parse("%41").unwrap().name, "%41");
+    // a decomposed accent is composed into a single character
+    assert_eq!(parse("e\u{301}").unwrap().name, "\u{c9}");
+    assert_eq!(
+        PageTitle::parse("euclid", &TitleConfig::default())
+            .unwrap()
+            .name,
+        "euclid"
+    );
+
+    assert!(matches!(parse(" _ / "), Err(TitleError::Empty)));
+    assert!(matches!(
+        PageTitle::parse_link("%FF", &config),
+        Err(TitleError::InvalidEncoding)
+    ));
+    assert!(matches!(
+        parse("a[[b]]"),
+        Err(TitleError::ForbiddenChar('['))
+    ));
+    assert!(matches!(
+        parse("line\nbreak"),
+        Err(TitleError::ForbiddenChar('\n'))
+    ));
+    assert!(parse(&"x".repeat(MAX_TITLE_LENGTH)).is_ok());
+    assert!(matches!(
+        parse(&format!("Talk:{}", "x".repeat(MAX_TITLE_LENGTH + 1))),
+        Err(TitleError::TooLong(256))
+    ));
+}